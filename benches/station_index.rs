@@ -0,0 +1,90 @@
+// per-keystroke cost of the station search, run with `cargo bench`
+// this is a binary crate, so the modules under test are pulled in by path
+use std::{hint::black_box, time::Instant};
+
+#[allow(dead_code, unused_imports)] // its tests run with the app, not here
+#[path = "../src/api.rs"]
+mod api;
+#[allow(dead_code)]
+#[cfg(feature = "offline-stations")]
+#[path = "../src/snapshot.rs"]
+mod snapshot;
+#[allow(dead_code)]
+#[path = "../src/station_index.rs"]
+mod station_index;
+
+use api::Station;
+use station_index::{SearchQuery, StationIndex};
+
+const ITERATIONS: u32 = 200;
+
+fn main() {
+    let file = std::fs::File::open("stations.json").expect("run the benchmark from the repo root");
+    let stations: Vec<Station> = serde_json::from_reader(file).expect("invalid stations.json");
+    println!("{} stations\n", stations.len());
+
+    let start = Instant::now();
+    let index = black_box(StationIndex::new(&stations));
+    println!("building the index: {:?}\n", start.elapsed());
+
+    // typing "Marienplatz" one character at a time
+    let typed = "Marienplatz";
+    println!(
+        "{:<14}{:>8}{:>16}{:>16}",
+        "query", "hits", "clone filter", "index"
+    );
+    for end in 1..=typed.len() {
+        let query = &typed[..end];
+        let old = measure(|| clone_filter(&stations, query).len());
+        let new = measure(|| index.search(query).len());
+        let hits = index.search(query).len();
+        println!("{:<14}{:>8}{:>14.1}µs{:>14.1}µs", query, hits, old, new);
+    }
+
+    println!();
+    println!(
+        "prefix \"haupt\":  {:.2}µs",
+        measure(|| index.prefix_search("haupt").len())
+    );
+    println!(
+        "id lookup:       {:.2}µs",
+        measure(|| index.by_id("de:09162:6").is_some() as usize)
+    );
+    println!(
+        "abbreviation:    {:.2}µs",
+        measure(|| index.by_abbreviation("KA").is_some() as usize)
+    );
+    println!(
+        "product UBAHN:   {:.2}µs",
+        measure(|| index.with_product("UBAHN").len())
+    );
+    println!(
+        "qualifiers:      {:.2}µs  (bahnhof place:freising has:sbahn)",
+        measure(|| index
+            .query(&SearchQuery::parse("bahnhof place:freising has:sbahn"))
+            .len())
+    );
+}
+
+// what get_suggested_station_list used to do on every keystroke and redraw
+fn clone_filter(stations: &[Station], query: &str) -> Vec<Station> {
+    stations
+        .iter()
+        .filter(|station| {
+            station
+                .name
+                .to_ascii_lowercase()
+                .contains(&query.to_ascii_lowercase())
+        })
+        .cloned()
+        .collect()
+}
+
+// average microseconds per call
+fn measure(mut f: impl FnMut() -> usize) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed().as_secs_f64() * 1_000_000.0 / f64::from(ITERATIONS)
+}
@@ -0,0 +1,43 @@
+// the build script only has work to do when the station snapshot is baked into the binary
+// without these lines cargo would rerun it on every change to the package, also without the feature
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=stations.json");
+    println!("cargo:rerun-if-changed=stations.date");
+    println!("cargo:rerun-if-env-changed=MVG_STATIONS_SNAPSHOT_DATE");
+
+    #[cfg(feature = "offline-stations")]
+    offline_stations::embed().expect("failed to embed the station snapshot");
+}
+
+#[cfg(feature = "offline-stations")]
+mod offline_stations {
+    use std::{env, fs, io::Write, path::Path};
+
+    use flate2::{write::GzEncoder, Compression};
+
+    const STATIONS_FILE: &str = "stations.json";
+    const DATE_FILE: &str = "stations.date";
+    const SNAPSHOT_DATE_VAR: &str = "MVG_STATIONS_SNAPSHOT_DATE";
+
+    pub fn embed() -> std::io::Result<()> {
+        let json = fs::read(STATIONS_FILE)?;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&json)?;
+        let compressed = encoder.finish()?;
+
+        let out_dir = env::var("OUT_DIR").expect("cargo always sets OUT_DIR");
+        fs::write(Path::new(&out_dir).join("stations.json.gz"), compressed)?;
+
+        // the modification time of stations.json is just the time of the checkout, so the date is kept next to it
+        // it can be overridden for a snapshot that was swapped in for the build
+        let snapshot_date = match env::var(SNAPSHOT_DATE_VAR) {
+            Ok(date) => date,
+            Err(_) => fs::read_to_string(DATE_FILE)?.trim().to_string(),
+        };
+        println!("cargo:rustc-env={}={}", SNAPSHOT_DATE_VAR, snapshot_date);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::api::DepartureInfo;
+
+// a later delay of at least this many minutes is reported again
+const DELAY_STEP: i64 = 2;
+
+// a reminder for one trip, the departures are replaced on every refresh,
+// so the trip is found again by its line, direction and planned time
+#[derive(Debug, Clone)]
+pub struct Alarm {
+    pub station_id: String,
+    pub label: String,
+    pub destination: String,
+    pub planned_departure_time: i64,
+    pub minutes_before: i64,
+    expected_departure_time: i64, // the realtime departure time of the last refresh
+    reported_delay: i64,          // in minutes
+    reported_cancelled: bool,
+    due: bool, // the reminder went off already
+}
+
+pub enum AlarmEvent {
+    Due(i64), // minutes until the departure
+    Cancelled,
+    Delayed(i64), // the new delay in minutes
+}
+
+impl Alarm {
+    pub fn new(station_id: &str, departure: &DepartureInfo, minutes_before: i64) -> Self {
+        Self {
+            station_id: station_id.to_string(),
+            label: departure.label.clone(),
+            destination: departure.destination.clone(),
+            planned_departure_time: departure.planned_departure_time,
+            minutes_before,
+            expected_departure_time: departure.realtime_departure_time,
+            reported_delay: departure.delay_in_minutes.unwrap_or(0),
+            reported_cancelled: departure.cancelled,
+            due: false,
+        }
+    }
+
+    pub fn matches(&self, departure: &DepartureInfo) -> bool {
+        departure.label == self.label
+            && departure.destination == self.destination
+            && departure.planned_departure_time == self.planned_departure_time
+    }
+
+    // compares the trip with the fresh departures of its station
+    pub fn update(&mut self, departures: &[DepartureInfo]) -> Vec<AlarmEvent> {
+        let mut events = vec![];
+        let Some(departure) = departures.iter().find(|departure| self.matches(departure)) else {
+            return events; // out of the list, either gone or not announced yet
+        };
+
+        self.expected_departure_time = departure.realtime_departure_time;
+        if departure.cancelled && !self.reported_cancelled {
+            self.reported_cancelled = true;
+            events.push(AlarmEvent::Cancelled);
+        }
+        let delay = departure.delay_in_minutes.unwrap_or(0);
+        if delay >= self.reported_delay + DELAY_STEP {
+            self.reported_delay = delay;
+            events.push(AlarmEvent::Delayed(delay));
+        }
+        events
+    }
+
+    // goes off once, when the trip is less than minutes_before away
+    pub fn check_due(&mut self, now: i64) -> Option<AlarmEvent> {
+        let left = self.expected_departure_time - now;
+        if self.due || self.reported_cancelled || left > self.minutes_before * 60_000 {
+            return None;
+        }
+        self.due = true;
+        Some(AlarmEvent::Due(left.max(0) / 60_000))
+    }
+
+    // the trip left, or would have left if it wasn't cancelled, nothing more to report
+    pub fn is_over(&self, now: i64) -> bool {
+        if self.reported_cancelled {
+            return now
+                > self
+                    .planned_departure_time
+                    .max(self.expected_departure_time);
+        }
+        self.due && now > self.expected_departure_time
+    }
+
+    pub fn describe(&self, event: &AlarmEvent) -> String {
+        match event {
+            AlarmEvent::Due(0) => format!("{} to {} leaves now", self.label, self.destination),
+            AlarmEvent::Due(minutes) => format!(
+                "{} to {} leaves in {} min",
+                self.label, self.destination, minutes
+            ),
+            AlarmEvent::Cancelled => format!("{} to {} is cancelled", self.label, self.destination),
+            AlarmEvent::Delayed(minutes) => format!(
+                "{} to {} is {} min late",
+                self.label, self.destination, minutes
+            ),
+        }
+    }
+}
+
+// rings the terminal bell and runs the configured command, if any
+// the command gets the message in $MVG_ALARM_MESSAGE, i.e. `notify-send "MVG" "$MVG_ALARM_MESSAGE"`
+pub fn notify(message: &str, command: Option<&str>) {
+    let mut stderr = std::io::stderr(); // the terminal the tui draws to
+    let _ = stderr.write_all(b"\x07");
+    let _ = stderr.flush();
+
+    if let Some(command) = command {
+        #[cfg(windows)]
+        let mut shell = Command::new("cmd");
+        #[cfg(windows)]
+        shell.arg("/C");
+        #[cfg(not(windows))]
+        let mut shell = Command::new("sh");
+        #[cfg(not(windows))]
+        shell.arg("-c");
+
+        // its output would end up in the middle of the tui
+        let child = shell
+            .arg(command)
+            .env("MVG_ALARM_MESSAGE", message)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(mut child) = child {
+            // waited for on the side, so that the command can take as long as it wants
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+// updates every alarm of the station and returns what should be reported
+pub fn update_alarms(
+    alarms: &mut [Alarm],
+    station_id: &str,
+    departures: &[DepartureInfo],
+) -> Vec<String> {
+    let mut messages = vec![];
+    for alarm in alarms
+        .iter_mut()
+        .filter(|alarm| alarm.station_id == station_id)
+    {
+        for event in alarm.update(departures) {
+            messages.push(alarm.describe(&event));
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60_000;
+    const PLANNED: i64 = 1_760_800_000_000;
+
+    fn departure(delay: i64, cancelled: bool) -> DepartureInfo {
+        DepartureInfo {
+            label: "U6".to_string(),
+            destination: "Klinikum Großhadern".to_string(),
+            planned_departure_time: PLANNED,
+            realtime_departure_time: PLANNED + delay * MINUTE,
+            delay_in_minutes: Some(delay),
+            cancelled,
+            ..DepartureInfo::default()
+        }
+    }
+
+    #[test]
+    fn goes_off_once_when_the_trip_is_close() {
+        let mut alarm = Alarm::new("de:09162:6", &departure(0, false), 5);
+        assert!(alarm.check_due(PLANNED - 6 * MINUTE).is_none());
+        assert!(matches!(
+            alarm.check_due(PLANNED - 5 * MINUTE),
+            Some(AlarmEvent::Due(5))
+        ));
+        assert!(alarm.check_due(PLANNED - 4 * MINUTE).is_none());
+    }
+
+    #[test]
+    fn a_delay_moves_the_alarm() {
+        let mut alarm = Alarm::new("de:09162:6", &departure(0, false), 5);
+        let events = alarm.update(&[departure(3, false)]);
+        assert!(matches!(events.as_slice(), [AlarmEvent::Delayed(3)]));
+        assert!(alarm.check_due(PLANNED - 3 * MINUTE).is_none());
+        assert!(alarm.check_due(PLANNED - 2 * MINUTE).is_some());
+        assert!(!alarm.is_over(PLANNED + MINUTE));
+        assert!(alarm.is_over(PLANNED + 3 * MINUTE + 1));
+    }
+
+    #[test]
+    fn small_delays_are_not_reported() {
+        let mut alarm = Alarm::new("de:09162:6", &departure(0, false), 5);
+        assert!(alarm.update(&[departure(1, false)]).is_empty());
+        assert!(matches!(
+            alarm.update(&[departure(2, false)]).as_slice(),
+            [AlarmEvent::Delayed(2)]
+        ));
+    }
+
+    #[test]
+    fn a_cancellation_is_reported_once_and_ends_the_alarm() {
+        let mut alarm = Alarm::new("de:09162:6", &departure(0, false), 5);
+        assert!(matches!(
+            alarm.update(&[departure(0, true)]).as_slice(),
+            [AlarmEvent::Cancelled]
+        ));
+        assert!(alarm.update(&[departure(0, true)]).is_empty());
+        assert!(alarm.check_due(PLANNED).is_none());
+        assert!(!alarm.is_over(PLANNED));
+        assert!(alarm.is_over(PLANNED + 1));
+    }
+
+    #[test]
+    fn an_alarm_on_a_cancelled_trip_ends_too() {
+        let alarm = Alarm::new("de:09162:6", &departure(0, true), 5);
+        assert!(alarm.is_over(PLANNED + 1));
+    }
+
+    #[test]
+    fn a_trip_missing_from_the_list_changes_nothing() {
+        let mut alarm = Alarm::new("de:09162:6", &departure(0, false), 5);
+        let mut other = departure(10, true);
+        other.label = "U3".to_string();
+        assert!(alarm.update(&[other]).is_empty());
+        assert!(!alarm.is_over(PLANNED + 1));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::api::DepartureInfo;
+
+// a service message and every line it was attached to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub text: String,
+    pub lines: Vec<String>, // labels, i.e. ["U3", "U6"], in the order they were first seen
+}
+
+// the api repeats the same messages on every departure they concern,
+// departures with the same banner_hash share one alert, messages without one are compared by their text
+// an alert with the same text as one seen before is the same alert, with or without a hash
+pub fn collect_alerts(departures: &[DepartureInfo]) -> Vec<Alert> {
+    let mut keys: Vec<String> = vec![];
+    let mut alerts: Vec<Alert> = vec![];
+
+    for departure in departures {
+        let messages: Vec<(String, String)> = if departure.banner_hash.is_empty() {
+            departure
+                .messages
+                .iter()
+                .map(|message| (message.clone(), message.clone()))
+                .collect()
+        } else if departure.messages.is_empty() {
+            vec![]
+        } else {
+            vec![(departure.banner_hash.clone(), departure.messages.join(" "))]
+        };
+
+        for (key, text) in messages {
+            let known = keys
+                .iter()
+                .position(|known| *known == key)
+                .or_else(|| alerts.iter().position(|alert| alert.text == text));
+            let i = match known {
+                Some(i) => i,
+                None => {
+                    keys.push(key);
+                    alerts.push(Alert {
+                        text,
+                        lines: vec![],
+                    });
+                    alerts.len() - 1
+                }
+            };
+            if !alerts[i].lines.contains(&departure.label) {
+                alerts[i].lines.push(departure.label.clone());
+            }
+        }
+    }
+
+    alerts
+}
+
+// every alert in one line for the ticker, i.e. "U3, U6: Bauarbeiten ... +++ "
+pub fn ticker_text(alerts: &[Alert]) -> String {
+    alerts
+        .iter()
+        .map(|alert| {
+            // messages can contain line breaks
+            let text = alert.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            format!("{}: {}  +++  ", alert.lines.join(", "), text)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn departure(label: &str, banner_hash: &str, messages: &[&str]) -> DepartureInfo {
+        DepartureInfo {
+            label: label.to_string(),
+            banner_hash: banner_hash.to_string(),
+            messages: messages.iter().map(|message| message.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn alert(text: &str, lines: &[&str]) -> Alert {
+        Alert {
+            text: text.to_string(),
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn departures_with_the_same_hash_share_an_alert() {
+        let departures = [
+            departure("U3", "abc", &["Bauarbeiten", "am Wochenende"]),
+            departure("U6", "abc", &["Bauarbeiten", "am Wochenende"]),
+            departure("U6", "def", &["Aufzug defekt"]),
+        ];
+        assert_eq!(
+            collect_alerts(&departures),
+            [
+                alert("Bauarbeiten am Wochenende", &["U3", "U6"]),
+                alert("Aufzug defekt", &["U6"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn messages_without_a_hash_are_matched_by_their_text() {
+        let departures = [
+            departure("19", "", &["Umleitung", "Verspätungen"]),
+            departure("N19", "", &["Umleitung"]),
+        ];
+        assert_eq!(
+            collect_alerts(&departures),
+            [
+                alert("Umleitung", &["19", "N19"]),
+                alert("Verspätungen", &["19"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn departures_without_messages_add_nothing() {
+        let departures = [departure("U3", "abc", &[]), departure("U6", "", &[])];
+        assert_eq!(collect_alerts(&departures), []);
+    }
+
+    #[test]
+    fn a_line_is_listed_once() {
+        let departures = [
+            departure("U3", "abc", &["Bauarbeiten"]),
+            departure("U3", "abc", &["Bauarbeiten"]),
+            departure("U3", "", &["Bauarbeiten"]),
+        ];
+        assert_eq!(collect_alerts(&departures), [alert("Bauarbeiten", &["U3"])]);
+    }
+
+    #[test]
+    fn the_same_text_with_and_without_a_hash_is_one_alert() {
+        let departures = [
+            departure("U3", "abc", &["Bauarbeiten"]),
+            departure("Bus 100", "", &["Bauarbeiten"]),
+            departure("U6", "def", &["Bauarbeiten"]),
+        ];
+        assert_eq!(
+            collect_alerts(&departures),
+            [alert("Bauarbeiten", &["U3", "Bus 100", "U6"])]
+        );
+    }
+}
@@ -1,9 +1,39 @@
-use std::fs::File;
+use std::{fs::File, time::Duration};
 
 // #[allow(unused, dead_code, unused_)]
 use anyhow::Result;
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
+// longer than the refresh interval, so that the connection to mvg.de is reused between refreshes
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+// the station list is about 1.5MB, the timeout of the client is meant for the small departure responses
+const STATIONS_TIMEOUT: Duration = Duration::from_secs(120);
+
+// one client for the whole app, it keeps the connections open across requests
+// without a proxy in the config, reqwest picks up HTTP_PROXY, HTTPS_PROXY and NO_PROXY from the environment
+pub fn build_client(
+    proxy: Option<&str>,
+    connect_timeout: Duration,
+    timeout: Duration,
+) -> Result<Client> {
+    let mut builder = Client::builder()
+        .user_agent(concat!(
+            "mvgfahrinfo/",
+            env!("CARGO_PKG_VERSION"),
+            " (+https://github.com/FaisalBinAhmed/MVGFahrinfo)"
+        ))
+        .connect_timeout(connect_timeout)
+        .timeout(timeout) // covers reading the response too, a hung connection must not freeze the ui
+        .gzip(true)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT);
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")] //to avoid renaming all the fields to snake_case
 pub struct StationInfo {
@@ -17,7 +47,7 @@ pub struct StationInfo {
     pub r#type: String, //type is a reserved keyword in Rust
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")] //to avoid renaming all the fields to snake_case
 pub struct DepartureInfo {
     pub planned_departure_time: i64,
@@ -39,11 +69,92 @@ pub struct DepartureInfo {
     pub stop_point_global_id: String, //"de:09162:6:52:52"
 }
 
-pub async fn get_departures(id: &str) -> Result<Vec<DepartureInfo>> {
-    let full_url = format!("https://www.mvg.de/api/bgw-pt/v3/departures?globalId={}", id);
+// identifies a trip across refreshes, the realtime data of a trip changes but these don't
+pub type TripKey = (String, String, i64); // label, destination, planned departure time
+
+impl DepartureInfo {
+    pub fn trip_key(&self) -> TripKey {
+        (
+            self.label.clone(),
+            self.destination.clone(),
+            self.planned_departure_time,
+        )
+    }
 
-    let resp = reqwest::get(full_url)
+    // what the signs at the station say, i.e. "Gleis 1" or "Bussteig 3"
+    pub fn stop_point_name(&self) -> String {
+        match (self.platform, self.transport_type.as_str()) {
+            (Some(platform), "BUS") => format!("Bussteig {}", platform),
+            (Some(platform), _) => format!("Gleis {}", platform),
+            // "de:09162:6:52:52", the last part numbers the stop point within the station
+            (None, _) => match self.stop_point_global_id.rsplit(':').next() {
+                Some(number) if !number.is_empty() => format!("Haltepunkt {}", number),
+                _ => "Unknown stop point".to_string(),
+            },
+        }
+    }
+}
+
+// the values the departures endpoint accepts for transportTypes
+pub const TRANSPORT_TYPES: [&str; 6] = ["UBAHN", "SBAHN", "TRAM", "BUS", "REGIONAL_BUS", "BAHN"];
+
+// the parameters of the departures endpoint, i.e. DepartureQuery::new(id).limit(40).offset_in_minutes(20)
+// everything that isn't set is left to the endpoint's defaults
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DepartureQuery {
+    global_id: String,
+    limit: Option<u32>,
+    offset_in_minutes: Option<i64>, // departures starting this many minutes from now
+    transport_types: Vec<String>,   // out of TRANSPORT_TYPES, empty for all of them
+}
+
+impl DepartureQuery {
+    pub fn new(global_id: &str) -> Self {
+        Self {
+            global_id: global_id.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset_in_minutes(mut self, minutes: i64) -> Self {
+        self.offset_in_minutes = Some(minutes);
+        self
+    }
+
+    pub fn transport_types(mut self, transport_types: &[String]) -> Self {
+        self.transport_types = transport_types.to_vec();
+        self
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        let mut parameters = vec![("globalId", self.global_id.clone())];
+        if let Some(limit) = self.limit {
+            parameters.push(("limit", limit.to_string()));
+        }
+        if let Some(minutes) = self.offset_in_minutes {
+            parameters.push(("offsetInMinutes", minutes.to_string()));
+        }
+        if !self.transport_types.is_empty() {
+            parameters.push(("transportTypes", self.transport_types.join(",")));
+        }
+        parameters
+    }
+}
+
+pub async fn get_departures(client: &Client, query: &DepartureQuery) -> Result<Vec<DepartureInfo>> {
+    let full_url = "https://www.mvg.de/api/bgw-pt/v3/departures";
+
+    let resp = client
+        .get(full_url)
+        .query(&query.parameters())
+        .send()
         .await?
+        .error_for_status()?
         .json::<Vec<DepartureInfo>>()
         .await?;
     // println!("{:#?}", resp[0]);
@@ -51,6 +162,59 @@ pub async fn get_departures(id: &str) -> Result<Vec<DepartureInfo>> {
     Ok(resp)
 }
 
+// a service message for the whole network, i.e. a strike or a closed track
+// the fields are optional in practice, so everything has a default
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServiceMessage {
+    pub title: String,
+    pub description: String,     // html
+    pub r#type: String,          // "INCIDENT", "SCHEDULE_CHANGE"
+    pub valid_from: Option<i64>, // utc time stamp in milliseconds
+    pub valid_to: Option<i64>,
+    pub lines: Vec<MessageLine>,
+    pub station_global_ids: Vec<String>, // "de:09162:6"
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MessageLine {
+    pub label: String,          // "U6"
+    pub transport_type: String, // "UBAHN"
+}
+
+// where the network-wide messages come from
+// the departures and stations only exist at MVG, but messages could also come from other operators later
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MessageProvider {
+    #[default]
+    Mvg,
+}
+
+impl MessageProvider {
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageProvider::Mvg => "MVG",
+        }
+    }
+
+    pub async fn get_messages(self, client: &Client) -> Result<Vec<ServiceMessage>> {
+        match self {
+            MessageProvider::Mvg => {
+                let full_url = "https://www.mvg.de/api/bgw-pt/v3/messages";
+                let messages = client
+                    .get(full_url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<Vec<ServiceMessage>>()
+                    .await?;
+                Ok(messages)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Station {
@@ -80,29 +244,93 @@ pub struct Station {
 //       "latitude":48.13951,
 //       "longitude":11.56613
 
+// where the station list was loaded from, so the ui can tell the user when the data might be outdated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StationSource {
+    File,
+    Api,
+    #[cfg(feature = "offline-stations")]
+    Snapshot, // compiled into the binary, see build.rs
+}
+
 // todo: we need a way to manually refrest this file
-pub async fn get_stations() -> Result<Vec<Station>> {
-    if let Ok(file) = File::open("stations.json") {
-        //todo: handle the error propagation here, it should fetch from api instead
-        let stations = serde_json::from_reader(file)?; //it inferres the type from the function return type and automatically deserializes it
-        Ok(stations)
-    } else {
-        let full_url = "https://www.mvg.de/.rest/zdm/stations";
-
-        let resp = reqwest::get(full_url).await?;
-
-        let stations = resp.json::<Vec<Station>>().await?;
-        match save_response_to_json_file(stations.clone()).await {
-            Ok(_) => println!("saved stations to file"),
-            Err(_) => println!("failed to save stations to file"),
-        }
-        Ok(stations)
+pub async fn get_stations(client: &Client) -> Result<(Vec<Station>, StationSource)> {
+    if let Ok(stations) = read_stations_file() {
+        return Ok((stations, StationSource::File));
+    }
+
+    match fetch_stations(client).await {
+        Ok(stations) => Ok((stations, StationSource::Api)),
+        #[cfg(feature = "offline-stations")]
+        Err(_) => Ok((crate::snapshot::load_stations()?, StationSource::Snapshot)),
+        #[cfg(not(feature = "offline-stations"))]
+        Err(e) => Err(e),
     }
 }
 
+fn read_stations_file() -> Result<Vec<Station>> {
+    let file = File::open("stations.json")?;
+    let stations = serde_json::from_reader(file)?; //it inferres the type from the function return type and automatically deserializes it
+    Ok(stations)
+}
+
+async fn fetch_stations(client: &Client) -> Result<Vec<Station>> {
+    let full_url = "https://www.mvg.de/.rest/zdm/stations";
+
+    let resp = client
+        .get(full_url)
+        .timeout(STATIONS_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let stations = resp.json::<Vec<Station>>().await?;
+    match save_response_to_json_file(stations.clone()).await {
+        Ok(_) => println!("saved stations to file"),
+        Err(_) => println!("failed to save stations to file"),
+    }
+    Ok(stations)
+}
+
 async fn save_response_to_json_file(station_response: Vec<Station>) -> Result<()> {
     let json = serde_json::to_string_pretty(&station_response)?;
     std::fs::write("stations.json", json)?; //stores this file in the root directory
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_station_is_required() {
+        assert_eq!(
+            DepartureQuery::new("de:09162:6").parameters(),
+            [("globalId", "de:09162:6".to_string())]
+        );
+    }
+
+    #[test]
+    fn every_option_becomes_a_parameter() {
+        let query = DepartureQuery::new("de:09162:6")
+            .limit(40)
+            .offset_in_minutes(20)
+            .transport_types(&["UBAHN".to_string(), "TRAM".to_string()]);
+        assert_eq!(
+            query.parameters(),
+            [
+                ("globalId", "de:09162:6".to_string()),
+                ("limit", "40".to_string()),
+                ("offsetInMinutes", "20".to_string()),
+                ("transportTypes", "UBAHN,TRAM".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_transport_types_means_all_of_them() {
+        let query = DepartureQuery::new("de:09162:6").transport_types(&[]);
+        assert_eq!(query.parameters().len(), 1);
+    }
+}
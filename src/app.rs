@@ -1,64 +1,339 @@
-use ratatui::widgets::ListState;
+use std::{
+    collections::HashMap,
+    ops::Range,
+    time::{Duration, Instant},
+};
 
-use crate::api;
+use ratatui::{
+    prelude::Rect,
+    widgets::{ListState, TableState},
+};
 
-#[derive(PartialEq)] // need this to do binary comparison
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alarm::{self, Alarm},
+    alerts::{self, Alert},
+    api, clock,
+    commands::{self, CommandSuggestion},
+    config::AlarmConfig,
+    departure_diff::{self, DepartureChange},
+    keymap::Keymap,
+    station_index::{SearchQuery, StationIndex},
+    theme::Theme,
+    update::REFRESH_INTERVAL,
+    user_data::UserData,
+};
+
+#[derive(PartialEq, Clone, Copy)] // need this to do binary comparison
 pub enum AppTabs {
     HomeTab,
     StationTab,
+    MessagesTab, // service messages for the whole network
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum AppMode {
     Normal,
     Search,
+    Details, // the popup for the selected departure
+    Help,    // the popup listing every key
+    Command, // the `:` palette
+    Alerts,  // the popup listing the service messages of the station
+}
+
+// where things were drawn in the last frame, so that mouse clicks can be mapped back to them
+#[derive(Default)]
+pub struct ScreenAreas {
+    pub tabs: Rect,
+    pub tab_titles: Vec<Range<u16>>, // columns of every tab title, in the order of AppTabs
+    pub station_list: Rect,          // without the border
+    pub departure_rows: Rect,        // without the border, padding and header
+    pub suggestions: Rect,
+    pub ticker: Rect, // empty without alerts
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
+}
+
+// how the Station List tab is sorted and split into sections
+#[derive(Clone, Copy, PartialEq)]
+pub enum StationGrouping {
+    None, // the order of stations.json
+    Alphabetical,
+    Zone,
+    Product,
+}
+
+impl StationGrouping {
+    pub fn next(self) -> Self {
+        match self {
+            StationGrouping::None => StationGrouping::Alphabetical,
+            StationGrouping::Alphabetical => StationGrouping::Zone,
+            StationGrouping::Zone => StationGrouping::Product,
+            StationGrouping::Product => StationGrouping::None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StationGrouping::None => "ungrouped",
+            StationGrouping::Alphabetical => "by name",
+            StationGrouping::Zone => "by zone",
+            StationGrouping::Product => "by product",
+        }
+    }
+}
+
+// a line in the Station List tab, the headers can't be selected
+pub enum StationListRow {
+    Header(String),
+    Station(usize), // index into stations
+}
+
+impl StationListRow {
+    // in terminal lines
+    pub fn height(&self) -> usize {
+        match self {
+            StationListRow::Header(_) => 1,
+            StationListRow::Station(_) => 2,
+        }
+    }
 }
 
+// how the departures table is split into rows
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DepartureGrouping {
+    #[default]
+    None, // one row per departure, in order
+    Line,      // one row per line and direction with the next ETAs
+    StopPoint, // a section for every platform or bus bay
+}
+
+impl DepartureGrouping {
+    pub fn next(self) -> Self {
+        match self {
+            DepartureGrouping::None => DepartureGrouping::Line,
+            DepartureGrouping::Line => DepartureGrouping::StopPoint,
+            DepartureGrouping::StopPoint => DepartureGrouping::None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DepartureGrouping::None => "",
+            DepartureGrouping::Line => "by line",
+            DepartureGrouping::StopPoint => "by stop point",
+        }
+    }
+}
+
+// how the ETAs of the departures are shown
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeDisplay {
+    Relative, // "4 min"
+    Clock,    // "18:34"
+}
+
+// a row of the departures table, the stop point headers can't be selected
+pub enum DepartureRow<'a> {
+    StopPoint(String),
+    Departures(Vec<&'a api::DepartureInfo>), // a single departure unless grouped by line
+}
+
+impl DepartureRow<'_> {
+    pub fn is_selectable(&self) -> bool {
+        matches!(self, DepartureRow::Departures(_))
+    }
+}
+
+// how long the status bar blinks when an alarm goes off, in ticks of 250ms
+const FLASH_TICKS: u8 = 8;
+
+// enough departures for a tall terminal and for the lines that only run once an hour
+const DEPARTURE_LIMIT: u32 = 60;
+
+// how far `]` and `[` move the time the departures start at
+pub const LOOK_AHEAD_STEP_MINUTES: i64 = 15;
+
+// letters typed within this time are combined into one type-ahead jump
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
+
 pub struct App {
     pub selected_tab: AppTabs,
     pub should_quit: bool,
     pub stations: Vec<api::Station>,
+    pub station_source: Option<api::StationSource>, // None if no stations could be loaded at all
+    pub station_index: StationIndex,
     pub selected_station: Option<api::Station>,
     pub departures: Vec<api::DepartureInfo>,
+    pub departure_scroll_state: TableState,
+    pub areas: ScreenAreas,
     pub should_redraw: bool,
+    pub focused: bool, // false while the terminal window is in the background
     pub status: String,
+    pub notice: Option<String>, // the outcome of the last command, shown in the status bar until the next key
     pub last_refreshed: String,
+    pub last_refreshed_at: Option<Instant>, // of the departures shown, None until the first refresh of a station
+    pub data_age: String,                   // i.e. "3 min ago", updated on tick
+    pub data_stale: bool,                   // older than the refresh interval
+    pub refresh_failures: tokio::sync::watch::Sender<u32>, // in a row, the auto refresh task backs off on it
+    pub theme: Theme,
+    pub departure_filter: Option<String>, // lowercase, matched against the line and the direction
+    pub departure_grouping: DepartureGrouping, // remembered per station
+    pub stop_point_filter: Option<String>, // a stop_point_global_id
+    pub departures_from: Option<i64>, // utc timestamp in milliseconds, None for departures from now on
+    pub transport_types: Vec<String>, // filtered by the api, empty for all of them
+    pub alerts: Vec<Alert>,           // the messages of all departures, without repeats
+    pub ticker_offset: usize,         // advanced on every tick to scroll the alerts ticker
+    pub alerts_scroll: u16,
+    //network messages tab
+    pub message_provider: api::MessageProvider,
+    pub network_messages: Vec<api::ServiceMessage>,
+    pub messages_refreshed: Option<String>, // None until the messages were fetched once
+    pub message_line_filter: Option<String>, // a line label, "U6"
+    pub messages_favorites_only: bool,
+    pub messages_scroll: u16,
+    //departure alarms
+    pub alarm_config: AlarmConfig,
+    pub alarms: Vec<Alarm>,
+    pub flash_ticks: u8, // the status bar blinks while this counts down
+    pub departure_changes: HashMap<api::TripKey, DepartureChange>, // highlighted for a moment after a refresh
     //scroll related
-    pub scroll_state: ListState,
+    pub scroll_state: ListState, // selects a position in station_order
+    pub station_grouping: StationGrouping,
+    pub station_order: Vec<usize>, // indices into stations, in the order they are listed
+    pub station_rows: Vec<StationListRow>,
+    pub station_row_of: Vec<usize>, // the row of every position in station_order
+    pub station_list_offset: usize, // the first visible row, only the visible rows are rendered
+    pub station_page_size: usize,   // stations per screen, updated on every draw
+    type_ahead: String,
+    type_ahead_at: Instant,
     //search related
     pub app_mode: AppMode,
     pub query: String,
     pub cursor_position: usize,
-    pub suggested_stations: Vec<api::Station>,
+    pub suggested_stations: Vec<usize>, // indices into stations
+    pub favorite_suggestions: usize, // how many of the suggested stations at the top are favorites
+    pub recent_suggestions: usize, // how many of the suggested stations after those come from the history
     pub search_scroll_state: ListState,
+    pub user_data: UserData,
+    pub keymap: Keymap,
+    pub http: reqwest::Client, // shared by every request, see api::build_client
+    pub help_scroll: u16,
+    //command palette, shares query and cursor_position with the search
+    pub command_suggestions: Vec<CommandSuggestion>,
+    pub command_scroll_state: ListState,
 }
 
 impl App {
-    pub async fn new() -> Self {
-        Self {
+    pub async fn new(keymap: Keymap, alarm_config: AlarmConfig, http: reqwest::Client) -> Self {
+        let (stations, station_source) = match api::get_stations(&http).await {
+            Ok((stations, source)) => (stations, Some(source)),
+            Err(_) => (vec![], None),
+        };
+        Self::with_stations(
+            stations,
+            station_source,
+            UserData::load(),
+            keymap,
+            alarm_config,
+            http,
+        )
+    }
+
+    // everything that doesn't need the network or the disk, the tests start here
+    pub fn with_stations(
+        stations: Vec<api::Station>,
+        station_source: Option<api::StationSource>,
+        user_data: UserData,
+        keymap: Keymap,
+        alarm_config: AlarmConfig,
+        http: reqwest::Client,
+    ) -> Self {
+        let station_index = StationIndex::new(&stations);
+        let theme = user_data
+            .theme
+            .as_deref()
+            .and_then(Theme::by_name)
+            .unwrap_or_default();
+        let mut app = Self {
             selected_tab: AppTabs::HomeTab,
             should_quit: false,
-            stations: api::get_stations().await.unwrap_or_else(|_| vec![]),
+            stations,
+            station_source,
+            station_index,
             selected_station: None,
             departures: vec![],
+            departure_scroll_state: TableState::default(),
+            areas: ScreenAreas::default(),
             should_redraw: true,
+            focused: true,
             status: "Loading stations...".to_string(),
+            notice: None,
             last_refreshed: " ".to_string(),
+            last_refreshed_at: None,
+            data_age: String::new(),
+            data_stale: false,
+            refresh_failures: tokio::sync::watch::channel(0).0,
+            theme,
+            departure_filter: None,
+            departure_grouping: DepartureGrouping::None,
+            stop_point_filter: None,
+            departures_from: None,
+            transport_types: vec![],
+            alerts: vec![],
+            ticker_offset: 0,
+            alerts_scroll: 0,
+            message_provider: api::MessageProvider::default(),
+            network_messages: vec![],
+            messages_refreshed: None,
+            message_line_filter: None,
+            messages_favorites_only: false,
+            messages_scroll: 0,
+            alarm_config,
+            alarms: vec![],
+            flash_ticks: 0,
+            departure_changes: HashMap::new(),
             scroll_state: ListState::default(),
+            station_grouping: StationGrouping::None,
+            station_order: vec![],
+            station_rows: vec![],
+            station_row_of: vec![],
+            station_list_offset: 0,
+            station_page_size: 10,
+            type_ahead: String::new(),
+            type_ahead_at: Instant::now(),
             app_mode: AppMode::Normal,
             query: String::new(),
             cursor_position: 0,
             search_scroll_state: ListState::default(),
             suggested_stations: vec![],
-        }
+            favorite_suggestions: 0,
+            recent_suggestions: 0,
+            user_data,
+            keymap,
+            http,
+            help_scroll: 0,
+            command_suggestions: vec![],
+            command_scroll_state: ListState::default(),
+        };
+        app.group_stations();
+        app
     }
     pub fn quit(&mut self) {
         self.should_quit = true;
     }
     pub fn increment_station(&mut self) {
+        if self.station_order.is_empty() {
+            return;
+        }
         let i = match self.scroll_state.selected() {
             Some(i) => {
-                if i >= self.stations.len() - 1 {
+                if i >= self.station_order.len() - 1 {
                     0
                 } else {
                     i + 1
@@ -70,10 +345,13 @@ impl App {
     }
 
     pub fn decrement_station(&mut self) {
+        if self.station_order.is_empty() {
+            return;
+        }
         let i = match self.scroll_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.stations.len() - 1
+                    self.station_order.len() - 1
                 } else {
                     i - 1
                 }
@@ -82,39 +360,384 @@ impl App {
         };
         self.scroll_state.select(Some(i));
     }
-    pub fn toggle_tabs(&mut self) {
-        match self.selected_tab {
-            AppTabs::HomeTab => self.selected_tab = AppTabs::StationTab,
-            AppTabs::StationTab => self.selected_tab = AppTabs::HomeTab,
-        }
+    pub async fn toggle_tabs(&mut self) {
+        let next = match self.selected_tab {
+            AppTabs::HomeTab => AppTabs::StationTab,
+            AppTabs::StationTab => AppTabs::MessagesTab,
+            AppTabs::MessagesTab => AppTabs::HomeTab,
+        };
+        self.select_tab(next).await;
     }
 
     pub async fn update_departures(&mut self) {
+        // once the chosen time has come, the departures are just the current ones again
+        if self
+            .departures_from
+            .is_some_and(|from| from <= chrono::Utc::now().timestamp_millis())
+        {
+            self.departures_from = None;
+        }
         if let Some(station) = &self.selected_station {
-            if let Ok(departures) = api::get_departures(&station.id).await {
+            let result = api::get_departures(&self.http, &self.departure_query(&station.id)).await;
+            // every attempt restarts the timer of the auto refresh task, see update::initiate_auto_refresh
+            self.refresh_failures.send_modify(|failures| match result {
+                Ok(_) => *failures = 0,
+                Err(_) => *failures += 1,
+            });
+            if let Ok(departures) = result {
                 // we don't update the departures if the api call returns an error variant
+                self.alerts = alerts::collect_alerts(&departures);
+                // the first departures of a station are all new, that is not worth highlighting
+                if !self.departures.is_empty() {
+                    self.departure_changes
+                        .extend(departure_diff::diff(&self.departures, &departures));
+                }
                 self.departures = departures;
+                let messages =
+                    alarm::update_alarms(&mut self.alarms, &station.id, &self.departures);
+                self.ring(messages);
+                self.clamp_departure_selection();
                 self.update_last_refreshed();
+                self.update_data_age();
                 self.should_redraw = true;
             }
         }
     }
 
+    fn departure_query(&self, id: &str) -> api::DepartureQuery {
+        let query = api::DepartureQuery::new(id)
+            .limit(DEPARTURE_LIMIT)
+            .transport_types(&self.transport_types);
+        match self.departures_from {
+            // rounded up, so that nothing before the chosen time shows up
+            Some(from) => query.offset_in_minutes(
+                (from - chrono::Utc::now().timestamp_millis() + 59_999) / 60_000,
+            ),
+            None => query,
+        }
+    }
+
+    // another time or other transport types give a different list, not a changed one
+    async fn reload_departures(&mut self) {
+        self.departures.clear();
+        self.departure_changes.clear();
+        self.last_refreshed_at = None; // nothing is shown, so nothing is fresh
+        self.departure_scroll_state = TableState::default();
+        self.update_departures().await;
+    }
+
+    // minutes from now mean little when looking at the evening in the morning
+    pub fn time_display(&self) -> TimeDisplay {
+        if self.user_data.clock_times || self.departures_from.is_some() {
+            TimeDisplay::Clock
+        } else {
+            TimeDisplay::Relative
+        }
+    }
+
+    pub fn toggle_clock_times(&mut self) {
+        self.user_data.clock_times = !self.user_data.clock_times;
+        let _ = self.user_data.save();
+    }
+
+    // a time in the past shows the departures from now on
+    pub async fn set_departures_from(&mut self, from: Option<i64>) {
+        self.departures_from = from.filter(|from| *from > chrono::Utc::now().timestamp_millis());
+        self.reload_departures().await;
+    }
+
+    // moves the start of the departures by the minutes, negative minutes go back towards now
+    pub async fn look_ahead(&mut self, minutes: i64) {
+        if self.departures_from.is_none() && minutes < 0 {
+            return; // already at now
+        }
+        let from = self
+            .departures_from
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+        self.set_departures_from(Some(from + minutes * 60_000))
+            .await;
+    }
+
+    pub async fn set_transport_types(&mut self, transport_types: Vec<String>) {
+        self.transport_types = transport_types;
+        self.reload_departures().await;
+    }
+
+    // auto refreshes are skipped while nobody is looking, the data is refreshed when focus returns
+    // alarms keep working in the background
+    pub async fn auto_refresh(&mut self) {
+        if self.focused {
+            self.update_departures().await;
+            if self.selected_tab == AppTabs::MessagesTab {
+                self.update_network_messages().await;
+            }
+        }
+        self.refresh_alarms().await;
+    }
+
+    // fetches the stations of the alarms that weren't refreshed with the departures just now
+    async fn refresh_alarms(&mut self) {
+        let refreshed = match &self.selected_station {
+            Some(station) if self.focused => Some(station.id.clone()),
+            _ => None,
+        };
+        let mut station_ids: Vec<String> = self
+            .alarms
+            .iter()
+            .map(|alarm| alarm.station_id.clone())
+            .filter(|id| Some(id) != refreshed.as_ref())
+            .collect();
+        station_ids.sort_unstable();
+        station_ids.dedup();
+
+        for id in station_ids {
+            // the alarm's trip has to be found whatever the departure view is showing
+            let query = api::DepartureQuery::new(&id).limit(DEPARTURE_LIMIT);
+            if let Ok(departures) = api::get_departures(&self.http, &query).await {
+                let messages = alarm::update_alarms(&mut self.alarms, &id, &departures);
+                self.ring(messages);
+            }
+        }
+    }
+
+    pub async fn update_network_messages(&mut self) {
+        match self.message_provider.get_messages(&self.http).await {
+            Ok(messages) => {
+                self.network_messages = messages;
+                self.messages_refreshed = Some(clock::now().format("%H:%M:%S").to_string());
+            }
+            // the old messages stay, they are better than nothing
+            Err(_) => {
+                self.notice = Some(format!(
+                    "Could not load the messages from {}",
+                    self.message_provider.name()
+                ))
+            }
+        }
+        self.should_redraw = true;
+    }
+
+    // the network messages matching the line filter, and only those for favorite stations if asked to
+    pub fn visible_messages(&self) -> Vec<&api::ServiceMessage> {
+        self.network_messages
+            .iter()
+            .filter(|message| match &self.message_line_filter {
+                Some(line) => message
+                    .lines
+                    .iter()
+                    .any(|l| l.label.eq_ignore_ascii_case(line)),
+                None => true,
+            })
+            .filter(|message| {
+                !self.messages_favorites_only
+                    || message
+                        .station_global_ids
+                        .iter()
+                        .any(|id| self.user_data.favorite_stations.contains(id))
+            })
+            .collect()
+    }
+
+    pub fn set_message_line_filter(&mut self, line: &str) {
+        let line = line.trim();
+        self.message_line_filter = (!line.is_empty()).then(|| line.to_uppercase());
+        self.messages_scroll = 0;
+    }
+
+    pub fn toggle_favorite_messages(&mut self) {
+        self.messages_favorites_only = !self.messages_favorites_only;
+        self.messages_scroll = 0;
+    }
+
+    // every line mentioned in the network messages, for completing the line filter
+    pub fn message_lines(&self) -> Vec<&str> {
+        let mut lines: Vec<&str> = self
+            .network_messages
+            .iter()
+            .flat_map(|message| message.lines.iter().map(|line| line.label.as_str()))
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
+    // scrolls the alerts ticker, blinks the status bar and checks the alarms
+    pub fn tick(&mut self) {
+        // alerts that fit stand still, there is nothing to redraw for them
+        let ticker_scrolls =
+            alerts::ticker_text(&self.alerts).chars().count() > self.areas.ticker.width as usize;
+        if self.focused && ticker_scrolls {
+            self.ticker_offset = self.ticker_offset.wrapping_add(1);
+            self.should_redraw = true;
+        }
+        if self.flash_ticks > 0 {
+            self.flash_ticks -= 1;
+            self.should_redraw = true;
+        }
+        let changes = self.departure_changes.len();
+        self.departure_changes.retain(|_, change| change.is_fresh());
+        if self.departure_changes.len() != changes {
+            self.should_redraw = true;
+        }
+        self.update_data_age();
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let due: Vec<String> = self
+            .alarms
+            .iter_mut()
+            .filter_map(|alarm| alarm.check_due(now).map(|event| alarm.describe(&event)))
+            .collect();
+        self.ring(due);
+        self.alarms.retain(|alarm| !alarm.is_over(now));
+    }
+
+    fn ring(&mut self, messages: Vec<String>) {
+        if messages.is_empty() {
+            return;
+        }
+        let message = messages.join(", ");
+        alarm::notify(&message, self.alarm_config.command.as_deref());
+        self.notice = Some(format!("⏰ {}", message));
+        self.flash_ticks = FLASH_TICKS;
+        self.should_redraw = true;
+    }
+
+    // sets an alarm for the selected departure, or removes it if it has one and no time is given
+    pub fn toggle_alarm(&mut self, minutes_before: Option<i64>) -> Result<()> {
+        let (Some(station), Some(departure)) = (&self.selected_station, self.selected_departure())
+        else {
+            bail!("select a departure first");
+        };
+
+        let existing = self
+            .alarms
+            .iter()
+            .position(|alarm| alarm.matches(departure));
+        match (existing, minutes_before) {
+            (Some(i), None) => {
+                let alarm = self.alarms.remove(i);
+                self.notice = Some(format!(
+                    "No alarm for {} to {}",
+                    alarm.label, alarm.destination
+                ));
+            }
+            (_, _) if departure.cancelled => {
+                bail!(
+                    "{} to {} is cancelled",
+                    departure.label,
+                    departure.destination
+                )
+            }
+            (existing, minutes_before) => {
+                let minutes_before = minutes_before.unwrap_or(self.alarm_config.minutes_before);
+                let alarm = Alarm::new(&station.id, departure, minutes_before);
+                self.notice = Some(format!(
+                    "Alarm {} min before {} to {} leaves",
+                    minutes_before, alarm.label, alarm.destination
+                ));
+                match existing {
+                    Some(i) => self.alarms[i] = alarm,
+                    None => self.alarms.push(alarm),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove_alarm(&mut self) -> Result<()> {
+        let Some(departure) = self.selected_departure() else {
+            bail!("select a departure first");
+        };
+        let count = self.alarms.len();
+        let departure = departure.clone();
+        self.alarms.retain(|alarm| !alarm.matches(&departure));
+        if self.alarms.len() == count {
+            bail!("the selected departure has no alarm");
+        }
+        self.notice = Some("Alarm removed".to_string());
+        Ok(())
+    }
+
+    pub fn focus_lost(&mut self) {
+        self.focused = false;
+    }
+
+    pub async fn focus_gained(&mut self) {
+        self.focused = true;
+        // switching back and forth between windows shouldn't hit the api every time
+        let stale = self
+            .last_refreshed_at
+            .is_none_or(|at| at.elapsed() >= REFRESH_INTERVAL);
+        if stale {
+            self.update_departures().await;
+        }
+        self.should_redraw = true;
+    }
+
     fn update_last_refreshed(&mut self) {
-        let time_now = chrono::Local::now();
+        let time_now = clock::now();
         self.last_refreshed = format!("{}", time_now.format("%H:%M:%S"));
+        self.last_refreshed_at = Some(Instant::now());
+    }
+
+    // only redraws when the shown age changes, which is at most once a minute
+    fn update_data_age(&mut self) {
+        let age = self.last_refreshed_at.map(|at| at.elapsed());
+        let data_age = match age {
+            None => String::new(),
+            Some(age) if age < Duration::from_secs(60) => "just now".to_string(),
+            Some(age) => format!("{} min ago", age.as_secs() / 60),
+        };
+        // a little slack, so that a refresh that is just under way doesn't count
+        let data_stale = age.is_some_and(|age| age > REFRESH_INTERVAL + Duration::from_secs(10));
+        if data_age != self.data_age || data_stale != self.data_stale {
+            self.data_age = data_age;
+            self.data_stale = data_stale;
+            self.should_redraw = true;
+        }
     }
 
     pub async fn select_station(&mut self) {
         self.selected_station = match self.scroll_state.selected() {
-            Some(i) => Some(self.stations[i].clone()),
+            Some(i) => Some(self.stations[self.station_order[i]].clone()),
             None => None,
         };
+        self.enter_selected_station();
         self.status = format!("Fetching departures");
         self.update_departures().await;
         self.selected_tab = AppTabs::HomeTab; // switch to home tab immidiatelyq
         self.should_redraw = true;
     }
+
+    // starts over with the newly selected station: its own grouping, no filter, no departures of the previous one
+    // it is also remembered as a recent station
+    fn enter_selected_station(&mut self) {
+        if let Some(station) = &self.selected_station {
+            self.departure_grouping = self.user_data.departure_grouping(&station.id);
+            self.stop_point_filter = None;
+            self.departure_scroll_state = TableState::default();
+            // the departures of the previous station are gone, not changed
+            self.departures.clear();
+            self.departure_changes.clear();
+            self.alerts.clear();
+            self.last_refreshed_at = None;
+            self.user_data.add_recent_station(&station.id);
+            let _ = self.user_data.save(); // losing the history is not worth interrupting the user for
+        }
+    }
+
+    pub fn clear_recent_stations(&mut self) {
+        self.user_data.clear_recent_stations();
+        let _ = self.user_data.save();
+        self.update_suggestions();
+    }
+
+    // used when the station is known up front, e.g. from the command line
+    pub async fn open_station(&mut self, i: usize) {
+        let position = self.station_order.iter().position(|station| *station == i);
+        self.scroll_state.select(position);
+        self.select_station().await;
+    }
 }
 
 //second impl block for the search mode and to keep the code clean
@@ -132,11 +755,14 @@ impl App {
     }
 
     pub fn enter_char(&mut self, new_char: char) {
-        if new_char.len_utf8() == 1 {
-            // temporary workaround: ignoring non-ascii characters that are more than 1 byte
-            self.query.insert(self.cursor_position, new_char);
-            self.move_cursor_right();
-        }
+        // the cursor counts characters, completed station names can contain umlauts
+        let index = self
+            .query
+            .char_indices()
+            .nth(self.cursor_position)
+            .map_or(self.query.len(), |(index, _)| index);
+        self.query.insert(index, new_char);
+        self.move_cursor_right();
         //should also commence the search
     }
 
@@ -196,11 +822,45 @@ impl App {
         self.search_scroll_state.select(Some(i));
     }
 
+    // called whenever the query changes, instead of filtering the whole station list on every redraw
+    // with an empty query the favorites and then the recently viewed stations are listed first
+    pub fn update_suggestions(&mut self) {
+        let mut suggestions = vec![];
+        if self.query.is_empty() {
+            let favorites = &self.user_data.favorite_stations;
+            suggestions.extend(
+                favorites
+                    .iter()
+                    .filter_map(|id| self.station_index.by_id(id)),
+            );
+            self.favorite_suggestions = suggestions.len();
+            suggestions.extend(
+                self.user_data
+                    .recent_stations
+                    .iter()
+                    .filter(|id| !favorites.contains(id))
+                    .filter_map(|id| self.station_index.by_id(id)),
+            );
+        } else {
+            self.favorite_suggestions = 0;
+        }
+        self.recent_suggestions = suggestions.len() - self.favorite_suggestions;
+        // the favorites and recent stations at the top aren't listed a second time
+        let pinned = suggestions.len();
+        for i in self.station_index.query(&SearchQuery::parse(&self.query)) {
+            if !suggestions[..pinned].contains(&i) {
+                suggestions.push(i);
+            }
+        }
+        self.suggested_stations = suggestions;
+    }
+
     pub async fn select_searched_station(&mut self) {
         self.selected_station = match self.search_scroll_state.selected() {
-            Some(i) => Some(self.suggested_stations[i].clone()),
+            Some(i) => Some(self.stations[self.suggested_stations[i]].clone()),
             None => None,
         };
+        self.enter_selected_station();
         self.status = format!("Fetching departures from search");
         self.suggested_stations.clear();
         self.search_scroll_state = ListState::default();
@@ -211,3 +871,716 @@ impl App {
         self.reset_cursor();
     }
 }
+
+//third impl block for paging, jumping and grouping in the Station List tab
+
+impl App {
+    pub fn page_down_stations(&mut self) {
+        let last = self.station_order.len().saturating_sub(1);
+        let i = match self.scroll_state.selected() {
+            Some(i) => (i + self.station_page_size).min(last),
+            None => 0,
+        };
+        self.scroll_state.select(Some(i));
+    }
+
+    pub fn page_up_stations(&mut self) {
+        let i = match self.scroll_state.selected() {
+            Some(i) => i.saturating_sub(self.station_page_size),
+            None => 0,
+        };
+        self.scroll_state.select(Some(i));
+    }
+
+    pub fn first_station(&mut self) {
+        self.scroll_state.select(Some(0));
+    }
+
+    pub fn last_station(&mut self) {
+        self.scroll_state
+            .select(Some(self.station_order.len().saturating_sub(1)));
+    }
+
+    // moves the visible window of rows just enough to keep the selected station on screen
+    // and returns the first visible row
+    pub fn scroll_station_rows(&mut self, height: usize) -> usize {
+        let rows = &self.station_rows;
+        let selected = self
+            .scroll_state
+            .selected()
+            .and_then(|position| self.station_row_of.get(position).copied());
+
+        let Some(selected) = selected else {
+            self.station_list_offset = self.station_list_offset.min(rows.len().saturating_sub(1));
+            return self.station_list_offset;
+        };
+
+        // scrolling up to the first station of a section also shows its header
+        let top = match selected.checked_sub(1).map(|row| &rows[row]) {
+            Some(StationListRow::Header(_)) => selected - 1,
+            _ => selected,
+        };
+        if top < self.station_list_offset {
+            self.station_list_offset = top;
+        }
+
+        // the first row that still leaves room for the selected one at the bottom
+        let mut lowest = selected;
+        let mut used = rows[selected].height();
+        while lowest > 0 && used + rows[lowest - 1].height() <= height {
+            lowest -= 1;
+            used += rows[lowest].height();
+        }
+        self.station_list_offset = self.station_list_offset.max(lowest);
+
+        self.station_list_offset
+    }
+
+    // a capital letter that isn't bound to an action starts a jump, unbound letters typed shortly after extend it
+    // bound keys always go to their action, so that the single letter shortcuts keep working
+    pub fn accepts_type_ahead(&self, c: char, is_bound: bool) -> bool {
+        self.selected_tab == AppTabs::StationTab
+            && !is_bound
+            && (c.is_uppercase() || (!self.type_ahead.is_empty() && self.type_ahead_is_fresh()))
+    }
+
+    pub fn type_ahead(&mut self, c: char) {
+        if !self.type_ahead_is_fresh() {
+            self.type_ahead.clear();
+        }
+        self.type_ahead.extend(c.to_lowercase());
+        self.type_ahead_at = Instant::now();
+
+        let index = &self.station_index;
+        let prefix = &self.type_ahead;
+        if let Some(position) = self
+            .station_order
+            .iter()
+            .position(|i| index.lowercase_name(*i).starts_with(prefix.as_str()))
+        {
+            self.scroll_state.select(Some(position));
+        }
+    }
+
+    fn type_ahead_is_fresh(&self) -> bool {
+        self.type_ahead_at.elapsed() < TYPE_AHEAD_TIMEOUT
+    }
+
+    pub fn toggle_station_grouping(&mut self) {
+        // keep the same station selected
+        let selected = self
+            .scroll_state
+            .selected()
+            .map(|position| self.station_order[position]);
+
+        self.station_grouping = self.station_grouping.next();
+        self.group_stations();
+
+        if let Some(i) = selected {
+            let position = self.station_order.iter().position(|station| *station == i);
+            self.scroll_state.select(position);
+        }
+    }
+
+    fn group_stations(&mut self) {
+        let grouping = self.station_grouping;
+        let stations = &self.stations;
+        let index = &self.station_index;
+
+        let mut order: Vec<usize> = (0..stations.len()).collect();
+        if grouping != StationGrouping::None {
+            order.sort_by_cached_key(|i| {
+                (
+                    group_of(&stations[*i], grouping).0,
+                    index.lowercase_name(*i).to_string(),
+                )
+            });
+        }
+
+        let mut rows = Vec::with_capacity(order.len());
+        let mut row_of = Vec::with_capacity(order.len());
+        let mut current_group = None;
+        for i in &order {
+            if grouping != StationGrouping::None {
+                let (rank, title) = group_of(&stations[*i], grouping);
+                if current_group.as_ref() != Some(&rank) {
+                    rows.push(StationListRow::Header(title));
+                    current_group = Some(rank);
+                }
+            }
+            row_of.push(rows.len());
+            rows.push(StationListRow::Station(*i));
+        }
+
+        self.station_order = order;
+        self.station_rows = rows;
+        self.station_row_of = row_of;
+    }
+}
+
+// sections are sorted by rank first, then by the text
+type GroupKey = (u32, String);
+
+// the sort key and title of the section a station is listed in
+fn group_of(station: &api::Station, grouping: StationGrouping) -> (GroupKey, String) {
+    match grouping {
+        StationGrouping::None => ((0, String::new()), String::new()),
+        StationGrouping::Alphabetical => {
+            let letter = match station.name.chars().next() {
+                Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+                _ => "#".to_string(),
+            };
+            ((0, letter.clone()), letter)
+        }
+        StationGrouping::Zone => {
+            // "m|1" is listed in its innermost zone
+            match station.tariff_zones.split('|').next().unwrap_or("") {
+                "" => ((u32::MAX, String::new()), "No zone".to_string()),
+                "m" => ((0, String::new()), "Zone M".to_string()),
+                zone => (
+                    (
+                        zone.parse::<u32>().map_or(u32::MAX - 1, |z| z + 1),
+                        zone.to_string(),
+                    ),
+                    format!("Zone {}", zone),
+                ),
+            }
+        }
+        StationGrouping::Product => {
+            const PRODUCTS: [(&str, &str); 5] = [
+                ("UBAHN", "U-Bahn"),
+                ("SBAHN", "S-Bahn"),
+                ("TRAM", "Tram"),
+                ("BUS", "Bus"),
+                ("BAHN", "Regional trains"),
+            ];
+            // listed under the fastest product that serves it
+            PRODUCTS
+                .iter()
+                .enumerate()
+                .find(|(_, (product, _))| station.products.iter().any(|p| p == product))
+                .map_or(
+                    ((PRODUCTS.len() as u32, String::new()), "Other".to_string()),
+                    |(rank, (_, title))| ((rank as u32, String::new()), title.to_string()),
+                )
+        }
+    }
+}
+
+//fourth impl block for the departures table and the mouse
+
+impl App {
+    // the departures matching the filters, the table and its selection only know about these
+    pub fn visible_departures(&self) -> Vec<&api::DepartureInfo> {
+        self.departures
+            .iter()
+            .filter(|departure| match &self.departure_filter {
+                Some(filter) => {
+                    departure.label.to_lowercase().contains(filter)
+                        || departure.destination.to_lowercase().contains(filter)
+                }
+                None => true,
+            })
+            .filter(|departure| match &self.stop_point_filter {
+                Some(stop_point) => departure.stop_point_global_id == *stop_point,
+                None => true,
+            })
+            .collect()
+    }
+
+    pub fn set_departure_filter(&mut self, filter: &str) {
+        let filter = filter.trim().to_lowercase();
+        self.departure_filter = (!filter.is_empty()).then_some(filter);
+        self.departure_scroll_state = TableState::default();
+    }
+
+    // the rows of the departures table: every visible departure on its own,
+    // grouped by line and direction in the order of their next departure,
+    // or below a header for every stop point
+    pub fn departure_rows(&self) -> Vec<DepartureRow<'_>> {
+        let departures = self.visible_departures();
+        match self.departure_grouping {
+            DepartureGrouping::None => departures
+                .into_iter()
+                .map(|departure| DepartureRow::Departures(vec![departure]))
+                .collect(),
+            DepartureGrouping::Line => {
+                let mut groups: Vec<Vec<&api::DepartureInfo>> = vec![];
+                let mut group_of: HashMap<(&str, &str), usize> = HashMap::new();
+                for departure in departures {
+                    let key = (departure.label.as_str(), departure.destination.as_str());
+                    match group_of.get(&key) {
+                        Some(i) => groups[*i].push(departure),
+                        None => {
+                            group_of.insert(key, groups.len());
+                            groups.push(vec![departure]);
+                        }
+                    }
+                }
+                groups.into_iter().map(DepartureRow::Departures).collect()
+            }
+            DepartureGrouping::StopPoint => {
+                let mut departures = departures;
+                // stable, so every stop point keeps its departures in order
+                departures.sort_by(|a, b| {
+                    (a.platform, &a.stop_point_global_id)
+                        .cmp(&(b.platform, &b.stop_point_global_id))
+                });
+                let mut rows = vec![];
+                let mut current = None;
+                for departure in departures {
+                    if current != Some(departure.stop_point_global_id.as_str()) {
+                        rows.push(DepartureRow::StopPoint(departure.stop_point_name()));
+                        current = Some(departure.stop_point_global_id.as_str());
+                    }
+                    rows.push(DepartureRow::Departures(vec![departure]));
+                }
+                rows
+            }
+        }
+    }
+
+    pub fn toggle_departure_grouping(&mut self) {
+        self.departure_grouping = self.departure_grouping.next();
+        self.departure_scroll_state = TableState::default();
+        if let Some(station) = &self.selected_station {
+            self.user_data
+                .set_departure_grouping(&station.id, self.departure_grouping);
+            let _ = self.user_data.save();
+        }
+    }
+
+    // only the departures of this stop point are shown, None shows all of them again
+    pub fn set_stop_point_filter(&mut self, stop_point: Option<String>) {
+        self.stop_point_filter = stop_point;
+        self.departure_scroll_state = TableState::default();
+    }
+
+    // shows only the stop point of the selected departure, or all of them if a stop point is shown already
+    pub fn toggle_stop_point_filter(&mut self) {
+        let stop_point = match &self.stop_point_filter {
+            Some(_) => None,
+            None => self
+                .selected_departure()
+                .map(|departure| departure.stop_point_global_id.clone()),
+        };
+        self.set_stop_point_filter(stop_point);
+    }
+
+    // every stop point of the current departures with its name, sorted like the stop point view
+    pub fn stop_points(&self) -> Vec<(String, String)> {
+        let mut stop_points: Vec<(Option<i64>, &str, String)> = vec![];
+        for departure in &self.departures {
+            let id = departure.stop_point_global_id.as_str();
+            if !stop_points.iter().any(|(_, known, _)| *known == id) {
+                stop_points.push((departure.platform, id, departure.stop_point_name()));
+            }
+        }
+        stop_points.sort();
+        stop_points
+            .into_iter()
+            .map(|(_, id, name)| (id.to_string(), name))
+            .collect()
+    }
+
+    // the list can get shorter between refreshes or when the filter changes
+    // the selection moves to the nearest departure, never onto a stop point header
+    fn clamp_departure_selection(&mut self) {
+        let Some(current) = self.departure_scroll_state.selected() else {
+            return;
+        };
+        let rows = self.departure_rows();
+        let nearest = (0..rows.len())
+            .filter(|i| rows[*i].is_selectable())
+            .min_by_key(|i| i.abs_diff(current));
+        self.departure_scroll_state.select(nearest);
+    }
+
+    // the stop point headers are skipped
+    pub fn next_departure(&mut self) {
+        let rows = self.departure_rows();
+        let selectable: Vec<usize> = (0..rows.len())
+            .filter(|i| rows[*i].is_selectable())
+            .collect();
+        let Some(first) = selectable.first() else {
+            return;
+        };
+        let i = match self.departure_scroll_state.selected() {
+            Some(current) => *selectable.iter().find(|i| **i > current).unwrap_or(first),
+            None => *first,
+        };
+        self.departure_scroll_state.select(Some(i));
+    }
+
+    pub fn previous_departure(&mut self) {
+        let rows = self.departure_rows();
+        let selectable: Vec<usize> = (0..rows.len())
+            .filter(|i| rows[*i].is_selectable())
+            .collect();
+        let Some(last) = selectable.last() else {
+            return;
+        };
+        let i = match self.departure_scroll_state.selected() {
+            Some(current) => *selectable
+                .iter()
+                .rev()
+                .find(|i| **i < current)
+                .unwrap_or(last),
+            None => *last,
+        };
+        self.departure_scroll_state.select(Some(i));
+    }
+
+    // the next departure of the selected group when the departures are grouped
+    pub fn selected_departure(&self) -> Option<&api::DepartureInfo> {
+        let i = self.departure_scroll_state.selected()?;
+        match self.departure_rows().get(i) {
+            Some(DepartureRow::Departures(group)) => Some(group[0]),
+            _ => None,
+        }
+    }
+
+    pub fn open_departure_details(&mut self) {
+        if self.selected_departure().is_some() {
+            self.app_mode = AppMode::Details;
+        }
+    }
+
+    pub fn open_alerts(&mut self) {
+        self.alerts_scroll = 0;
+        self.app_mode = AppMode::Alerts;
+    }
+
+    pub fn open_help(&mut self) {
+        self.help_scroll = 0;
+        self.app_mode = AppMode::Help;
+    }
+
+    // the network messages are only fetched once somebody looks at them
+    pub async fn select_tab(&mut self, tab: AppTabs) {
+        self.selected_tab = tab;
+        if tab == AppTabs::MessagesTab && self.messages_refreshed.is_none() {
+            self.update_network_messages().await;
+        }
+    }
+
+    pub fn tab_at(&self, column: u16, row: u16) -> Option<AppTabs> {
+        if !contains(self.areas.tabs, column, row) {
+            return None;
+        }
+        let tabs = [AppTabs::HomeTab, AppTabs::StationTab, AppTabs::MessagesTab];
+        self.areas
+            .tab_titles
+            .iter()
+            .position(|columns| columns.contains(&column))
+            .map(|i| tabs[i])
+    }
+
+    // position in station_order of the station drawn at this cell, headers don't count
+    pub fn station_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.areas.station_list;
+        if !contains(area, column, row) {
+            return None;
+        }
+        let mut top = area.top();
+        for (i, station_row) in self
+            .station_rows
+            .iter()
+            .enumerate()
+            .skip(self.station_list_offset)
+        {
+            top += station_row.height() as u16;
+            if row < top {
+                return self.station_row_of.binary_search(&i).ok();
+            }
+        }
+        None
+    }
+
+    pub fn departure_at(&self, column: u16, row: u16) -> Option<usize> {
+        if !contains(self.areas.departure_rows, column, row) {
+            return None;
+        }
+        let i =
+            self.departure_scroll_state.offset() + (row - self.areas.departure_rows.top()) as usize;
+        self.departure_rows()
+            .get(i)
+            .is_some_and(|departure_row| departure_row.is_selectable())
+            .then_some(i)
+    }
+
+    // the suggestions of the search or of the command palette, whichever is open
+    pub fn suggestion_at(&self, column: u16, row: u16) -> Option<usize> {
+        if !contains(self.areas.suggestions, column, row) {
+            return None;
+        }
+        let (scroll_state, count) = match self.app_mode {
+            AppMode::Command => (&self.command_scroll_state, self.command_suggestions.len()),
+            _ => (&self.search_scroll_state, self.suggested_stations.len()),
+        };
+        let i = scroll_state.offset() + (row - self.areas.suggestions.top()) as usize;
+        (i < count).then_some(i)
+    }
+}
+
+//fifth impl block for the command palette and what its commands change
+
+impl App {
+    // the line can be prefilled, i.e. with "at " to pick a time
+    pub fn open_command_palette(&mut self, line: &str) {
+        self.query = line.to_string();
+        self.cursor_position = line.len();
+        self.app_mode = AppMode::Command;
+        self.update_command_suggestions();
+    }
+
+    pub fn update_command_suggestions(&mut self) {
+        self.command_suggestions = commands::suggestions(self, &self.query);
+        self.command_scroll_state = ListState::default();
+    }
+
+    pub fn next_command_suggestion(&mut self) {
+        let count = self.command_suggestions.len();
+        if count == 0 {
+            return;
+        }
+        let i = match self.command_scroll_state.selected() {
+            Some(i) if i + 1 < count => i + 1,
+            _ => 0,
+        };
+        self.command_scroll_state.select(Some(i));
+    }
+
+    pub fn previous_command_suggestion(&mut self) {
+        let count = self.command_suggestions.len();
+        if count == 0 {
+            return;
+        }
+        let i = match self.command_scroll_state.selected() {
+            Some(0) | None => count - 1,
+            Some(i) => i - 1,
+        };
+        self.command_scroll_state.select(Some(i));
+    }
+
+    // replaces the input with the selected suggestion, or the first one if none is selected
+    pub fn complete_command(&mut self) {
+        let i = self.command_scroll_state.selected().unwrap_or(0);
+        if i >= self.command_suggestions.len() {
+            return;
+        }
+        let completed = self.command_suggestions.remove(i);
+        self.query = completed.text.clone();
+        self.cursor_position = self.query.chars().count();
+        self.update_command_suggestions();
+        // equal names in different places are told apart by the station, so Enter has to run that one
+        if completed.station.is_some() {
+            let position = self
+                .command_suggestions
+                .iter()
+                .position(|suggestion| suggestion.station == completed.station);
+            let position = position.unwrap_or_else(|| {
+                self.command_suggestions.insert(0, completed);
+                0
+            });
+            self.command_scroll_state.select(Some(position));
+        }
+    }
+
+    // a selected command that still needs its argument is completed instead of run
+    pub async fn run_command(&mut self) {
+        let selected = self
+            .command_scroll_state
+            .selected()
+            .and_then(|i| self.command_suggestions.get(i));
+        let (line, station) = match selected {
+            Some(suggestion) if suggestion.text.ends_with(' ') => {
+                self.complete_command();
+                return;
+            }
+            Some(suggestion) => (suggestion.text.clone(), suggestion.station),
+            None => (self.query.clone(), None),
+        };
+
+        self.app_mode = AppMode::Normal;
+        self.query.clear();
+        self.reset_cursor();
+        self.command_suggestions.clear();
+        if let Err(error) = commands::execute(self, &line, station).await {
+            self.notice = Some(error.to_string());
+        }
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.user_data.theme = Some(theme.name.to_string());
+        let _ = self.user_data.save();
+        self.notice = Some(format!("Theme: {}", theme.name));
+    }
+
+    pub fn add_favorite_station(&mut self) -> Result<()> {
+        let Some(station) = &self.selected_station else {
+            bail!("open a station first to add it to the favorites");
+        };
+        self.notice = Some(if self.user_data.add_favorite_station(&station.id) {
+            format!("Added {} to the favorites", station.name)
+        } else {
+            format!("{} already is a favorite", station.name)
+        });
+        self.user_data.save()
+    }
+
+    pub fn remove_favorite_station(&mut self) -> Result<()> {
+        let Some(station) = &self.selected_station else {
+            bail!("open a station first to remove it from the favorites");
+        };
+        if !self.user_data.remove_favorite_station(&station.id) {
+            bail!("{} is not a favorite", station.name);
+        }
+        self.notice = Some(format!("Removed {} from the favorites", station.name));
+        self.user_data.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeymapConfig;
+
+    fn station(id: &str, name: &str, place: &str) -> api::Station {
+        api::Station {
+            name: name.to_string(),
+            place: place.to_string(),
+            id: id.to_string(),
+            diva_id: 0,
+            abbreviation: None,
+            tariff_zones: "m".to_string(),
+            products: vec!["UBAHN".to_string()],
+            latitude: 0.0,
+            longitude: 0.0,
+        }
+    }
+
+    fn app(stations: Vec<api::Station>) -> App {
+        App::with_stations(
+            stations,
+            None,
+            UserData::default(),
+            Keymap::new(&KeymapConfig::default()).unwrap(),
+            AlarmConfig::default(),
+            reqwest::Client::new(),
+        )
+    }
+
+    fn type_command(app: &mut App, text: &str) {
+        app.app_mode = AppMode::Command;
+        for c in text.chars() {
+            app.enter_char(c);
+        }
+        app.update_command_suggestions();
+    }
+
+    // two sections, a header is one line and a station two
+    fn app_with_sections() -> App {
+        let mut app = app((0..5)
+            .map(|i| station(&i.to_string(), &format!("Station {}", i), "München"))
+            .collect());
+        app.station_order = (0..5).collect();
+        app.station_rows = vec![
+            StationListRow::Header("A".to_string()),
+            StationListRow::Station(0),
+            StationListRow::Station(1),
+            StationListRow::Station(2),
+            StationListRow::Header("B".to_string()),
+            StationListRow::Station(3),
+            StationListRow::Station(4),
+        ];
+        app.station_row_of = vec![1, 2, 3, 5, 6];
+        app
+    }
+
+    fn scroll_to(app: &mut App, position: usize, height: usize) -> usize {
+        app.scroll_state.select(Some(position));
+        app.scroll_station_rows(height)
+    }
+
+    #[test]
+    fn scrolling_down_keeps_the_selected_station_at_the_bottom() {
+        let mut app = app_with_sections();
+        assert_eq!(scroll_to(&mut app, 0, 6), 0);
+        assert_eq!(scroll_to(&mut app, 1, 6), 0);
+        // the header and three stations are 7 lines
+        assert_eq!(scroll_to(&mut app, 2, 6), 1);
+        assert_eq!(scroll_to(&mut app, 4, 6), 4);
+        // moving up within the window doesn't scroll
+        assert_eq!(scroll_to(&mut app, 3, 6), 4);
+    }
+
+    #[test]
+    fn scrolling_up_to_the_first_station_of_a_section_shows_its_header() {
+        let mut app = app_with_sections();
+        assert_eq!(scroll_to(&mut app, 4, 4), 5);
+        assert_eq!(scroll_to(&mut app, 3, 4), 4);
+        assert_eq!(scroll_to(&mut app, 2, 4), 3);
+        assert_eq!(scroll_to(&mut app, 0, 4), 0);
+    }
+
+    #[test]
+    fn an_empty_list_stays_at_the_top() {
+        let mut app = app(vec![]);
+        assert_eq!(app.scroll_station_rows(10), 0);
+        app.scroll_state.select(Some(3));
+        app.station_list_offset = 7;
+        assert_eq!(app.scroll_station_rows(10), 0);
+    }
+
+    #[test]
+    fn typing_after_a_completed_name_with_umlauts_appends() {
+        let mut app = app(vec![station("de:09162:1", "Görresstraße", "München")]);
+        type_command(&mut app, "station görres");
+        app.complete_command();
+        assert_eq!(app.query, "station Görresstraße");
+
+        app.enter_char('!');
+        assert_eq!(app.query, "station Görresstraße!");
+        // in front of the ß
+        for _ in 0..3 {
+            app.move_cursor_left();
+        }
+        app.enter_char('s');
+        assert_eq!(app.query, "station Görresstrasße!");
+    }
+
+    #[test]
+    fn completing_a_shared_name_keeps_the_chosen_station() {
+        let mut app = app(vec![
+            station("de:09178:1", "Bahnhof", "Freising"),
+            station("de:09174:1", "Bahnhof", "Dachau"),
+        ]);
+        type_command(&mut app, "station bahnhof");
+        app.next_command_suggestion();
+        app.next_command_suggestion();
+        assert_eq!(app.command_suggestions[1].station, Some(1));
+
+        app.complete_command();
+        assert_eq!(app.query, "station Bahnhof");
+        let selected = app.command_scroll_state.selected().unwrap();
+        assert_eq!(app.command_suggestions[selected].station, Some(1));
+    }
+
+    #[tokio::test]
+    async fn a_shared_name_without_a_chosen_station_is_reported() {
+        let mut app = app(vec![
+            station("de:09178:1", "Bahnhof", "Freising"),
+            station("de:09174:1", "Bahnhof", "Dachau"),
+        ]);
+        let error = commands::execute(&mut app, "station Bahnhof", None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'Bahnhof' matches 2 stations, i.e. Bahnhof (Freising), Bahnhof (Dachau), pick one of the suggestions"
+        );
+        assert!(app.selected_station.is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{self, BufRead, Write};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{api::Station, commands, station_index::StationIndex};
+
+const USAGE: &str = "\
+Get up-to-date departure times for Munich public transport in your terminal.
+
+Usage: mvgfahrinfo [OPTIONS]
+
+Options:
+  -s, --station <STATION>  Open the departures of a station by id (de:09162:6),
+                           abbreviation (KA) or name (\"Marienplatz\")
+  -a, --at <TIME>          Show the departures from a later time on,
+                           i.e. \"18:30\" or \"2026-10-18 18:30\"
+  -h, --help               Print this help
+  -V, --version            Print the version";
+
+// more than this and a numbered list stops being helpful
+const MAX_CANDIDATES: usize = 30;
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub station: Option<String>,
+    pub at: Option<i64>, // utc timestamp in milliseconds
+}
+
+impl Args {
+    // no need for a full argument parser crate for a handful of flags
+    pub fn parse() -> Result<Self> {
+        let mut args = Self::default();
+        let mut raw = std::env::args().skip(1);
+
+        while let Some(arg) = raw.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                "-V" | "--version" => {
+                    println!("mvgfahrinfo {}", env!("CARGO_PKG_VERSION"));
+                    std::process::exit(0);
+                }
+                "-s" | "--station" => {
+                    let value = raw
+                        .next()
+                        .ok_or_else(|| anyhow!("{} needs a station, see --help", arg))?;
+                    args.station = Some(value);
+                }
+                "-a" | "--at" => {
+                    let value = raw
+                        .next()
+                        .ok_or_else(|| anyhow!("{} needs a time, see --help", arg))?;
+                    args.at = Some(commands::parse_time(&value)?);
+                }
+                _ => match arg.split_once('=') {
+                    Some(("--station", value)) => args.station = Some(value.to_string()),
+                    Some(("--at", value)) => args.at = Some(commands::parse_time(value)?),
+                    _ => bail!("unknown argument '{}', see --help", arg),
+                },
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+// turns the --station argument into an index into the station list
+// asks on the terminal if the name is shared by several stations, before the tui takes over the screen
+pub fn resolve_station(stations: &[Station], index: &StationIndex, query: &str) -> Result<usize> {
+    let candidates = index.resolve(query);
+
+    match candidates.as_slice() {
+        [] => bail!("no station matches '{}'", query),
+        [only] => Ok(*only),
+        _ if candidates.len() > MAX_CANDIDATES => bail!(
+            "{} stations match '{}', please be more specific",
+            candidates.len(),
+            query
+        ),
+        _ => choose_station(stations, &candidates),
+    }
+}
+
+fn choose_station(stations: &[Station], candidates: &[usize]) -> Result<usize> {
+    println!("Several stations match, which one did you mean?");
+    for (number, i) in candidates.iter().enumerate() {
+        let station = &stations[*i];
+        println!(
+            "{:>3}) {}, {} ({})",
+            number + 1,
+            station.name,
+            station.place,
+            station.tariff_zones
+        );
+    }
+
+    let stdin = io::stdin();
+    loop {
+        print!("Station number: ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            bail!("no station chosen");
+        }
+        match line.trim().parse::<usize>() {
+            Ok(number) if (1..=candidates.len()).contains(&number) => {
+                return Ok(candidates[number - 1])
+            }
+            _ => println!("Please enter a number between 1 and {}", candidates.len()),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::{Europe::Berlin, Tz};
+
+// every time is shown in Munich time, also when the app runs on a server that is set to utc
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&Berlin)
+}
+
+// the api sends utc timestamps in milliseconds
+pub fn from_timestamp(timestamp: i64) -> Option<DateTime<Tz>> {
+    DateTime::from_timestamp_millis(timestamp).map(|time| time.with_timezone(&Berlin))
+}
+
+// a wall clock time in Munich, None if it is skipped by the change to summer time
+// when the clocks go back, the first of the two 02:30 is taken
+pub fn from_wall_clock(time: NaiveDateTime) -> Option<DateTime<Tz>> {
+    Berlin.from_local_datetime(&time).earliest()
+}
+
+// i.e. "18:30"
+pub fn format_time(timestamp: i64) -> String {
+    match from_timestamp(timestamp) {
+        Some(time) => time.format("%H:%M").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+// like format_time, with the date for any other day than today
+pub fn format_day_time(timestamp: i64) -> String {
+    match from_timestamp(timestamp) {
+        Some(time) if time.date_naive() == now().date_naive() => time.format("%H:%M").to_string(),
+        Some(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn wall_clock(day: u32, month: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn times_skipped_by_summer_time_dont_exist() {
+        assert_eq!(from_wall_clock(wall_clock(29, 3, 2, 30)), None);
+        assert!(from_wall_clock(wall_clock(29, 3, 3, 30)).is_some());
+    }
+
+    #[test]
+    fn the_first_of_two_equal_times_is_taken_when_the_clocks_go_back() {
+        let time = from_wall_clock(wall_clock(25, 10, 2, 30)).unwrap();
+        // still summer time, 00:30 utc, the second 02:30 is an hour later
+        assert_eq!(time.to_rfc3339(), "2026-10-25T02:30:00+02:00");
+        assert_eq!(
+            time.timestamp_millis(),
+            Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0)
+                .unwrap()
+                .timestamp_millis()
+        );
+    }
+
+    #[test]
+    fn times_are_shown_in_munich_on_both_sides_of_a_change() {
+        let utc = |month, day, hour| {
+            Utc.with_ymd_and_hms(2026, month, day, hour, 30, 0)
+                .unwrap()
+                .timestamp_millis()
+        };
+        // winter time is utc+1, summer time utc+2
+        assert_eq!(format_time(utc(3, 29, 0)), "01:30");
+        assert_eq!(format_time(utc(3, 29, 1)), "03:30");
+        assert_eq!(format_time(utc(10, 25, 0)), "02:30");
+        assert_eq!(format_time(utc(10, 25, 1)), "02:30");
+        assert_eq!(format_time(utc(10, 25, 2)), "03:30");
+    }
+}
@@ -0,0 +1,571 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use crate::{
+    api::TRANSPORT_TYPES,
+    app::{App, AppTabs},
+    clock,
+    station_index::SearchQuery,
+    theme::{Theme, THEMES},
+};
+
+// the most suggestions the palette lists for an argument, the station list is long
+const MAX_ARGUMENT_SUGGESTIONS: usize = 50;
+
+// a command of the `:` palette
+pub struct Command {
+    pub name: &'static str,
+    pub argument: Option<&'static str>, // shown as a placeholder, i.e. "<name>"
+    pub description: &'static str,
+}
+
+pub const COMMANDS: [Command; 14] = [
+    Command {
+        name: "station",
+        argument: Some("<name>"),
+        description: "show the departures of a station",
+    },
+    Command {
+        name: "filter",
+        argument: Some("<text>"),
+        description: "only show departures whose line or direction contains the text",
+    },
+    Command {
+        name: "stop",
+        argument: Some("<stop point>"),
+        description: "only show departures from one platform or bus bay",
+    },
+    Command {
+        name: "at",
+        argument: Some("<[YYYY-MM-DD] HH:MM|now>"),
+        description: "show the departures from a later time on",
+    },
+    Command {
+        name: "type",
+        argument: Some("<ubahn,tram,...|all>"),
+        description: "only load departures of some transport types",
+    },
+    Command {
+        name: "line",
+        argument: Some("<line>"),
+        description: "only show network messages for a line",
+    },
+    Command {
+        name: "notify",
+        argument: Some("[minutes|off]"),
+        description: "toggle an alarm for the selected departure, or set its minutes",
+    },
+    Command {
+        name: "refresh",
+        argument: None,
+        description: "reload departures",
+    },
+    Command {
+        name: "theme",
+        argument: Some("<name>"),
+        description: "change the colors",
+    },
+    Command {
+        name: "fav add",
+        argument: None,
+        description: "add the current station to the favorites",
+    },
+    Command {
+        name: "fav remove",
+        argument: None,
+        description: "remove the current station from the favorites",
+    },
+    Command {
+        name: "history clear",
+        argument: None,
+        description: "forget the recently viewed stations",
+    },
+    Command {
+        name: "help",
+        argument: None,
+        description: "show all keys",
+    },
+    Command {
+        name: "quit",
+        argument: None,
+        description: "close app",
+    },
+];
+
+// a line of the palette, completing to it replaces the whole input with `text`
+pub struct CommandSuggestion {
+    pub text: String,
+    pub description: String,
+    pub station: Option<usize>, // set when the suggestion is a station argument, index into stations
+}
+
+// commands whose name fuzzily matches what was typed so far, best first,
+// or the possible arguments once a command and a space have been typed
+pub fn suggestions(app: &App, line: &str) -> Vec<CommandSuggestion> {
+    if let Some((command, argument)) = exact_command(line) {
+        if command.argument.is_some() {
+            return argument_suggestions(app, command, argument);
+        }
+    }
+
+    let typed = line.trim();
+    let mut matches: Vec<(i64, &Command)> = COMMANDS
+        .iter()
+        .filter_map(|command| fuzzy_score(typed, command.name).map(|score| (score, command)))
+        .collect();
+    if !typed.is_empty() {
+        matches.sort_by_key(|(score, _)| -score); // stable, ties keep the order of COMMANDS
+    }
+
+    matches
+        .into_iter()
+        .map(|(_, command)| CommandSuggestion {
+            text: match command.argument {
+                Some(_) => format!("{} ", command.name),
+                None => command.name.to_string(),
+            },
+            description: match command.argument {
+                Some(argument) => format!("{}  {}", argument, command.description),
+                None => command.description.to_string(),
+            },
+            station: None,
+        })
+        .collect()
+}
+
+fn argument_suggestions(app: &App, command: &Command, argument: &str) -> Vec<CommandSuggestion> {
+    match command.name {
+        "station" => app
+            .station_index
+            .query(&SearchQuery::parse(argument))
+            .into_iter()
+            .take(MAX_ARGUMENT_SUGGESTIONS)
+            .map(|i| {
+                let station = &app.stations[i];
+                CommandSuggestion {
+                    text: format!("station {}", station.name),
+                    description: format!("{} ({})", station.place, station.tariff_zones),
+                    station: Some(i),
+                }
+            })
+            .collect(),
+        "theme" => THEMES
+            .iter()
+            .filter(|theme| fuzzy_score(argument.trim(), theme.name).is_some())
+            .map(|theme| CommandSuggestion {
+                text: format!("theme {}", theme.name),
+                description: if theme.name == app.theme.name {
+                    "current theme".to_string()
+                } else {
+                    String::new()
+                },
+                station: None,
+            })
+            .collect(),
+        "line" => app
+            .message_lines()
+            .into_iter()
+            .filter(|line| fuzzy_score(argument.trim(), line).is_some())
+            .map(|line| CommandSuggestion {
+                text: format!("line {}", line),
+                description: String::new(),
+                station: None,
+            })
+            .collect(),
+        "type" => {
+            // completes the last of the comma separated types
+            let (before, last) = argument.rsplit_once(',').unwrap_or(("", argument));
+            let prefix = if before.is_empty() {
+                String::new()
+            } else {
+                format!("{},", before.trim())
+            };
+            TRANSPORT_TYPES
+                .iter()
+                .map(|transport_type| transport_type.to_lowercase())
+                .chain(std::iter::once("all".to_string()))
+                .filter(|transport_type| fuzzy_score(last.trim(), transport_type).is_some())
+                .map(|transport_type| CommandSuggestion {
+                    text: format!("type {}{}", prefix, transport_type),
+                    description: String::new(),
+                    station: None,
+                })
+                .collect()
+        }
+        "stop" => app
+            .stop_points()
+            .into_iter()
+            .filter(|(_, name)| fuzzy_score(argument.trim(), name).is_some())
+            .map(|(id, name)| CommandSuggestion {
+                text: format!("stop {}", name),
+                description: id,
+                station: None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// runs a line of the palette, i.e. "station Marienplatz" or just "ref"
+// the station of a selected suggestion is opened directly, so that equal names in different places work
+pub async fn execute(app: &mut App, line: &str, station: Option<usize>) -> Result<()> {
+    let (command, argument) = parse(line)?;
+
+    match command.name {
+        "station" => {
+            let i = match station {
+                Some(i) => i,
+                None if argument.is_empty() => {
+                    bail!("station needs a name, i.e. station Marienplatz")
+                }
+                None => match app.station_index.resolve(argument)[..] {
+                    [] => bail!("no station matches '{}'", argument),
+                    [i] => i,
+                    ref matches => {
+                        let names: Vec<String> = matches
+                            .iter()
+                            .take(3)
+                            .map(|i| {
+                                let station = &app.stations[*i];
+                                format!("{} ({})", station.name, station.place)
+                            })
+                            .collect();
+                        bail!(
+                            "'{}' matches {} stations, i.e. {}, pick one of the suggestions",
+                            argument,
+                            matches.len(),
+                            names.join(", ")
+                        )
+                    }
+                },
+            };
+            app.open_station(i).await;
+        }
+        "filter" => app.set_departure_filter(argument),
+        "at" => match argument {
+            "" | "now" => app.set_departures_from(None).await,
+            time => app.set_departures_from(Some(parse_time(time)?)).await,
+        },
+        "type" => {
+            let transport_types = match argument {
+                "" | "all" => vec![],
+                types => types
+                    .split(',')
+                    .map(|transport_type| {
+                        let transport_type = transport_type.trim().to_uppercase();
+                        TRANSPORT_TYPES
+                            .contains(&transport_type.as_str())
+                            .then_some(transport_type)
+                            .ok_or_else(|| {
+                                anyhow!(
+                                    "unknown transport type, choose from {}",
+                                    TRANSPORT_TYPES.join(", ").to_lowercase()
+                                )
+                            })
+                    })
+                    .collect::<Result<Vec<String>>>()?,
+            };
+            app.set_transport_types(transport_types).await;
+        }
+        "line" => {
+            app.set_message_line_filter(argument);
+            app.select_tab(AppTabs::MessagesTab).await;
+        }
+        "stop" if argument.is_empty() => app.set_stop_point_filter(None),
+        "stop" => {
+            let id = app
+                .stop_points()
+                .into_iter()
+                .filter_map(|(id, name)| fuzzy_score(argument, &name).map(|score| (score, id)))
+                .max_by_key(|(score, _)| *score)
+                .map(|(_, id)| id)
+                .ok_or_else(|| anyhow!("no stop point matches '{}'", argument))?;
+            app.set_stop_point_filter(Some(id));
+        }
+        "notify" => match argument {
+            "off" => app.remove_alarm()?,
+            "" => app.toggle_alarm(None)?, // like the key
+            minutes => {
+                let minutes = minutes
+                    .trim_end_matches("min")
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|minutes| *minutes >= 0)
+                    .ok_or_else(|| anyhow!("notify needs a number of minutes or off"))?;
+                app.toggle_alarm(Some(minutes))?
+            }
+        },
+        "refresh" => app.update_departures().await,
+        "theme" => {
+            let theme = Theme::by_name(argument).ok_or_else(|| {
+                let names: Vec<&str> = THEMES.iter().map(|theme| theme.name).collect();
+                anyhow!(
+                    "unknown theme '{}', choose one of {}",
+                    argument,
+                    names.join(", ")
+                )
+            })?;
+            app.set_theme(theme);
+        }
+        "fav add" => app.add_favorite_station()?,
+        "fav remove" => app.remove_favorite_station()?,
+        "history clear" => app.clear_recent_stations(),
+        "help" => app.open_help(),
+        "quit" => app.quit(),
+        _ => unreachable!("every command in COMMANDS is handled"),
+    }
+    Ok(())
+}
+
+// "2026-10-18 18:30", or just "18:30" for the next time the clock shows it
+// as a utc timestamp in milliseconds, also used for --at
+pub fn parse_time(text: &str) -> Result<i64> {
+    parse_time_at(text, clock::now())
+}
+
+fn parse_time_at(text: &str, now: DateTime<Tz>) -> Result<i64> {
+    let text = text.trim();
+    let at = match chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+        Ok(at) => at,
+        Err(_) => {
+            let time = chrono::NaiveTime::parse_from_str(text, "%H:%M")
+                .map_err(|_| anyhow!("'{}' is not a time like 18:30 or 2026-10-18 18:30", text))?;
+            let mut day = now.date_naive();
+            if time <= now.time() {
+                day = day.succ_opt().unwrap_or(day);
+            }
+            day.and_time(time)
+        }
+    };
+    let at = clock::from_wall_clock(at)
+        .ok_or_else(|| anyhow!("{} doesn't exist on that day", at.format("%H:%M")))?;
+    if at <= now {
+        bail!("{} has already passed", at.format("%Y-%m-%d %H:%M"));
+    }
+    Ok(at.timestamp_millis())
+}
+
+// a command name typed in full wins, otherwise the best fuzzy match of a command without an argument
+// or of the first word for a command with one
+// commands with a subcommand like fav add change saved data and have to be typed in full
+fn parse(line: &str) -> Result<(&'static Command, &str)> {
+    if let Some((command, argument)) = exact_command(line) {
+        return Ok((command, argument.trim()));
+    }
+
+    let line = line.trim();
+    if line.is_empty() {
+        bail!("type a command, i.e. station Marienplatz");
+    }
+    let (first_word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let subcommands: Vec<&str> = COMMANDS
+        .iter()
+        .filter_map(|command| command.name.strip_prefix(first_word)?.strip_prefix(' '))
+        .collect();
+    if !subcommands.is_empty() {
+        bail!("{} needs {}", first_word, subcommands.join(" or "));
+    }
+
+    COMMANDS
+        .iter()
+        .filter(|command| !command.name.contains(' '))
+        .filter_map(|command| match command.argument {
+            None => fuzzy_score(line, command.name).map(|score| (score, command, "")),
+            Some(_) => fuzzy_score(first_word, command.name).map(|score| (score, command, rest)),
+        })
+        .max_by_key(|(score, _, _)| *score)
+        .map(|(_, command, argument)| (command, argument.trim()))
+        .ok_or_else(|| anyhow!("unknown command '{}'", line))
+}
+
+// the command whose full name starts the line, with everything after it
+fn exact_command(line: &str) -> Option<(&'static Command, &str)> {
+    let line = line.trim_start();
+    COMMANDS.iter().find_map(|command| {
+        let rest = line.strip_prefix(command.name)?;
+        (rest.is_empty() || rest.starts_with(' ')).then_some((command, rest))
+    })
+}
+
+// the characters of the pattern have to appear in the text in the same order,
+// matches at the start of a word and runs of consecutive characters score higher
+fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for c in pattern.chars().flat_map(char::to_lowercase) {
+        let found = position + text[position..].iter().position(|t| *t == c)?;
+        score += 1;
+        if found == 0 || text[found - 1] == ' ' {
+            score += 3;
+        }
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 2;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    // shorter names win among equal matches
+    Some(score * 100 - text.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{AlarmConfig, KeymapConfig},
+        keymap::Keymap,
+        user_data::UserData,
+    };
+
+    fn app() -> App {
+        App::with_stations(
+            vec![],
+            None,
+            UserData::default(),
+            Keymap::new(&KeymapConfig::default()).unwrap(),
+            AlarmConfig::default(),
+            reqwest::Client::new(),
+        )
+    }
+
+    // 2026-10-19 18:00 in Munich
+    fn now() -> DateTime<Tz> {
+        clock::from_wall_clock(
+            chrono::NaiveDate::from_ymd_opt(2026, 10, 19)
+                .unwrap()
+                .and_hms_opt(18, 0, 0)
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn time(text: &str) -> String {
+        match parse_time_at(text, now()) {
+            Ok(timestamp) => clock::from_timestamp(timestamp)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    fn parsed(line: &str) -> (&'static str, &str) {
+        let (command, argument) = parse(line).unwrap();
+        (command.name, argument)
+    }
+
+    fn texts(suggestions: Vec<CommandSuggestion>) -> Vec<String> {
+        suggestions
+            .into_iter()
+            .map(|suggestion| suggestion.text)
+            .collect()
+    }
+
+    #[test]
+    fn fuzzy_matches_need_the_characters_in_order() {
+        assert!(fuzzy_score("ref", "refresh").is_some());
+        assert!(fuzzy_score("REF", "refresh").is_some());
+        assert!(fuzzy_score("rfs", "refresh").is_some());
+        assert_eq!(fuzzy_score("ts", "station"), None);
+        assert_eq!(fuzzy_score("x", "refresh"), None);
+    }
+
+    #[test]
+    fn word_starts_and_runs_score_higher() {
+        // s and t start station, in history clear they are in the middle of a word
+        assert!(fuzzy_score("st", "station") > fuzzy_score("st", "history clear"));
+        assert!(fuzzy_score("hc", "history clear") > fuzzy_score("hc", "help"));
+        // the same match in a shorter name
+        assert!(fuzzy_score("fav", "fav add") > fuzzy_score("fav", "fav remove"));
+    }
+
+    #[test]
+    fn a_full_command_name_is_taken_with_its_argument() {
+        assert_eq!(parsed("station Marienplatz "), ("station", "Marienplatz"));
+        assert_eq!(parsed("  refresh"), ("refresh", ""));
+        assert_eq!(parsed("fav add"), ("fav add", ""));
+        assert!(exact_command("stationx").is_none());
+        assert!(exact_command("stop 1")
+            .is_some_and(|(command, rest)| { command.name == "stop" && rest == " 1" }));
+    }
+
+    #[test]
+    fn anything_else_runs_the_best_fuzzy_match() {
+        assert_eq!(parsed("ref"), ("refresh", ""));
+        assert_eq!(parsed("q"), ("quit", ""));
+        assert_eq!(parsed("th light"), ("theme", "light"));
+        assert_eq!(
+            parse("xyz").err().map(|error| error.to_string()),
+            Some("unknown command 'xyz'".to_string())
+        );
+        assert!(parse(" ").is_err());
+    }
+
+    #[test]
+    fn subcommands_have_to_be_typed_in_full() {
+        let error = |line| parse(line).err().map(|error| error.to_string());
+        assert_eq!(error("fav"), Some("fav needs add or remove".to_string()));
+        assert_eq!(error("fav ad"), Some("fav needs add or remove".to_string()));
+        assert_eq!(error("history"), Some("history needs clear".to_string()));
+        // but they are still suggested
+        assert_eq!(texts(suggestions(&app(), "fav")), ["fav add", "fav remove"]);
+    }
+
+    #[test]
+    fn without_input_every_command_is_suggested_in_order() {
+        let names: Vec<String> = COMMANDS
+            .iter()
+            .map(|command| match command.argument {
+                Some(_) => format!("{} ", command.name),
+                None => command.name.to_string(),
+            })
+            .collect();
+        assert_eq!(texts(suggestions(&app(), "")), names);
+    }
+
+    #[test]
+    fn a_time_of_day_is_the_next_time_the_clock_shows_it() {
+        assert_eq!(time("18:30"), "2026-10-19 18:30");
+        assert_eq!(time(" 17:30 "), "2026-10-20 17:30");
+        // the current minute has begun already
+        assert_eq!(time("18:00"), "2026-10-20 18:00");
+    }
+
+    #[test]
+    fn a_date_has_to_be_in_the_future() {
+        assert_eq!(time("2026-10-21 06:15"), "2026-10-21 06:15");
+        assert_eq!(
+            time("2026-10-19 17:59"),
+            "2026-10-19 17:59 has already passed"
+        );
+    }
+
+    #[test]
+    fn anything_else_is_not_a_time() {
+        assert_eq!(
+            time("half past six"),
+            "'half past six' is not a time like 18:30 or 2026-10-18 18:30"
+        );
+        assert_eq!(
+            time("25:00"),
+            "'25:00' is not a time like 18:30 or 2026-10-18 18:30"
+        );
+        assert_eq!(time("2027-03-28 02:30"), "02:30 doesn't exist on that day");
+    }
+
+    #[test]
+    fn the_last_transport_type_is_completed() {
+        let app = app();
+        assert_eq!(
+            texts(suggestions(&app, "type ubahn,tr")),
+            ["type ubahn,tram"]
+        );
+        assert_eq!(texts(suggestions(&app, "type sb")), ["type sbahn"]);
+    }
+}
@@ -0,0 +1,74 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Padding, Paragraph, Wrap},
+};
+
+use ratatui::prelude::Rect;
+
+use crate::{
+    alerts::{self, Alert},
+    components::static_widgets,
+    theme::Theme,
+};
+
+// the line above the status bar, moves one character per tick when the alerts don't fit
+pub fn get_ticker_widget(alerts: &[Alert], offset: usize, width: usize) -> Paragraph<'static> {
+    let text: Vec<char> = alerts::ticker_text(alerts).chars().collect();
+    let visible: String = if text.len() <= width {
+        text.iter().collect()
+    } else {
+        text.iter()
+            .cycle()
+            .skip(offset % text.len())
+            .take(width)
+            .collect()
+    };
+
+    Paragraph::new(Line::from(Span::styled(
+        visible,
+        Style::default().fg(Color::Black).bg(Color::LightYellow),
+    )))
+    .style(Style::default().bg(Color::LightYellow))
+}
+
+// every alert of the station with the lines it affects
+pub fn get_alerts_widget<'a>(alerts: &'a [Alert], theme: &Theme, scroll: u16) -> Paragraph<'a> {
+    Paragraph::new(alert_lines(alerts, theme))
+        .wrap(Wrap { trim: true })
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .title(format!(" ⚠ Alerts ({}) ", alerts.len()))
+                .borders(Borders::ALL)
+                .padding(Padding::new(2, 2, 1, 1)),
+        )
+}
+
+pub fn alerts_max_scroll(alerts: &[Alert], theme: &Theme, area: Rect) -> u16 {
+    static_widgets::max_scroll(&alert_lines(alerts, theme), area, true)
+}
+
+fn alert_lines<'a>(alerts: &'a [Alert], theme: &Theme) -> Vec<Line<'a>> {
+    let mut lines = vec![];
+
+    for alert in alerts {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            alert.lines.join(", "),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(alert.text.as_str()));
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No service alerts for this station.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+    lines
+}
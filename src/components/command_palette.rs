@@ -0,0 +1,26 @@
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+    widgets::{List, ListItem},
+};
+
+use crate::App;
+
+// the suggestions below the input of the `:` palette
+pub fn get_command_suggestion_list(app: &App) -> List<'_> {
+    let items = app
+        .command_suggestions
+        .iter()
+        .map(|suggestion| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<32}", suggestion.text), Style::default()),
+                Span::styled(
+                    suggestion.description.as_str(),
+                    Style::default().fg(app.theme.muted),
+                ),
+            ]))
+        })
+        .collect::<Vec<ListItem>>();
+
+    List::new(items)
+}
@@ -0,0 +1,76 @@
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Padding, Paragraph, Wrap},
+};
+
+use crate::{api::DepartureInfo, clock::format_day_time, theme::Theme};
+
+// opened by clicking a departure row or pressing enter on it
+pub fn get_departure_details_widget<'a>(
+    departure: &'a DepartureInfo,
+    theme: &Theme,
+) -> Paragraph<'a> {
+    let delay = match departure.delay_in_minutes {
+        Some(0) => "on time".to_string(),
+        Some(minutes) => format!("{} min", minutes),
+        None if departure.realtime => "on time".to_string(),
+        None => "no realtime data".to_string(),
+    };
+
+    let mut lines = vec![
+        detail_line(theme, "Line", departure.label.as_str()),
+        detail_line(theme, "Towards", departure.destination.as_str()),
+        detail_line(
+            theme,
+            "Planned",
+            format_day_time(departure.planned_departure_time),
+        ),
+        detail_line(
+            theme,
+            "Expected",
+            format_day_time(departure.realtime_departure_time),
+        ),
+        detail_line(theme, "Delay", delay),
+        detail_line(
+            theme,
+            "Platform",
+            departure
+                .platform
+                .map_or("-".to_string(), |platform| platform.to_string()),
+        ),
+        detail_line(theme, "Occupancy", departure.occupancy.to_lowercase()),
+        detail_line(theme, "Stop point", departure.stop_point_global_id.as_str()),
+    ];
+
+    if departure.cancelled {
+        lines.push(Line::from(Span::styled(
+            "This departure is cancelled.",
+            Style::default().fg(Color::LightRed),
+        )));
+    }
+    if departure.sev {
+        lines.push(Line::from(Span::styled(
+            "Replacement service (SEV).",
+            Style::default().fg(Color::LightYellow),
+        )));
+    }
+    for message in &departure.messages {
+        lines.push(Line::from(""));
+        lines.push(Line::from(message.as_str()));
+    }
+
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(format!(" {} → {} ", departure.label, departure.destination))
+            .borders(Borders::ALL)
+            .padding(Padding::new(2, 2, 1, 1)),
+    )
+}
+
+fn detail_line<'a>(theme: &Theme, name: &'a str, value: impl Into<String>) -> Line<'a> {
+    Line::from(vec![
+        Span::styled(format!("{:<12}", name), Style::default().fg(theme.muted)),
+        Span::raw(value.into()),
+    ])
+}
@@ -0,0 +1,70 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Padding, Paragraph},
+};
+
+use ratatui::prelude::Rect;
+
+use crate::{
+    components::static_widgets,
+    keymap::{self, Keymap},
+    theme::Theme,
+};
+
+// built from the active keymap, the same bindings the key handling uses
+pub fn get_help_widget<'a>(keymap: &'a Keymap, theme: &Theme, scroll: u16) -> Paragraph<'a> {
+    Paragraph::new(help_lines(keymap, theme))
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .title(" ? Keys ")
+                .borders(Borders::ALL)
+                .padding(Padding::new(2, 2, 1, 1)),
+        )
+}
+
+pub fn help_max_scroll(keymap: &Keymap, theme: &Theme, area: Rect) -> u16 {
+    static_widgets::max_scroll(&help_lines(keymap, theme), area, false)
+}
+
+fn help_lines<'a>(keymap: &'a Keymap, theme: &Theme) -> Vec<Line<'a>> {
+    let mut lines = vec![];
+
+    for mode in keymap::modes() {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{} mode", capitalize(keymap::mode_name(mode))),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )));
+
+        for (action, keys) in keymap.bindings(mode) {
+            let keys = match keys {
+                [] => "unbound".to_string(),
+                _ => keys
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" / "),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<24}", keys), Style::default().fg(theme.text)),
+                Span::styled(action.description(), Style::default().fg(theme.muted)),
+            ]));
+        }
+    }
+
+    lines
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
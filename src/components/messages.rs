@@ -0,0 +1,117 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Padding, Paragraph, Wrap},
+};
+
+use ratatui::prelude::Rect;
+
+use crate::{
+    api::ServiceMessage,
+    clock,
+    components::{static_widgets, station_list::get_line_badge},
+    theme::Theme,
+};
+
+// the Disruptions tab, every message with its lines, validity and text
+pub fn get_messages_widget<'a>(
+    messages: &[&'a ServiceMessage],
+    theme: &Theme,
+    title: String,
+    scroll: u16,
+) -> Paragraph<'a> {
+    Paragraph::new(message_lines(messages, theme))
+        .wrap(Wrap { trim: true })
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .padding(Padding::new(2, 2, 1, 1)),
+        )
+}
+
+pub fn messages_max_scroll(messages: &[&ServiceMessage], theme: &Theme, area: Rect) -> u16 {
+    static_widgets::max_scroll(&message_lines(messages, theme), area, true)
+}
+
+fn message_lines<'a>(messages: &[&'a ServiceMessage], theme: &Theme) -> Vec<Line<'a>> {
+    let mut lines = vec![];
+
+    for message in messages {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+
+        let mut heading = vec![];
+        for line in &message.lines {
+            heading.push(get_line_badge(&line.label, &line.transport_type));
+            heading.push(Span::raw(" "));
+        }
+        heading.push(Span::styled(
+            message.title.as_str(),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ));
+        lines.push(Line::from(heading));
+
+        if let Some(validity) = format_validity(message.valid_from, message.valid_to) {
+            lines.push(Line::from(Span::styled(
+                validity,
+                Style::default().fg(theme.muted),
+            )));
+        }
+        for paragraph in strip_html(&message.description).lines() {
+            if !paragraph.trim().is_empty() {
+                lines.push(Line::from(paragraph.trim().to_string()));
+            }
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No messages.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+    lines
+}
+
+// i.e. "18.10. 06:00 – 20.10. 23:00"
+fn format_validity(from: Option<i64>, to: Option<i64>) -> Option<String> {
+    let format = |timestamp: i64| {
+        clock::from_timestamp(timestamp).map(|time| time.format("%d.%m. %H:%M").to_string())
+    };
+    match (from.and_then(format), to.and_then(format)) {
+        (Some(from), Some(to)) => Some(format!("{} – {}", from, to)),
+        (Some(from), None) => Some(format!("since {}", from)),
+        (None, Some(to)) => Some(format!("until {}", to)),
+        (None, None) => None,
+    }
+}
+
+// the descriptions are small html snippets, paragraphs and line breaks become new lines
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut tag = None;
+    for c in html.chars() {
+        match (c, &mut tag) {
+            ('<', None) => tag = Some(String::new()),
+            ('>', Some(name)) => {
+                let name = name.trim_start_matches('/').to_lowercase();
+                if name.starts_with("br") || name == "p" || name == "li" {
+                    text.push('\n');
+                }
+                tag = None;
+            }
+            (c, Some(name)) => name.push(c),
+            (c, None) => text.push(c),
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
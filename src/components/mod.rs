@@ -1,3 +1,8 @@
+pub mod alerts;
+pub mod command_palette;
+pub mod departure_details;
+pub mod help;
+pub mod messages;
 pub mod static_widgets;
 pub mod station_list;
 // I expose the static_widgets module from the components module.
@@ -1,8 +1,13 @@
 use ratatui::{
     prelude::{Alignment, Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
     widgets::{Block, BorderType, Borders},
 };
 
+// the help, the alerts and the messages are drawn in a bordered box with a padding of 2 columns and 1 row
+const SCROLL_BOX_WIDTH: u16 = 2 + 4;
+const SCROLL_BOX_HEIGHT: u16 = 2 + 2;
+
 pub fn get_app_border() -> Block<'static> {
     return Block::default()
         .borders(Borders::ALL)
@@ -30,3 +35,106 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         ])
         .split(popup_layout[1])[1]
 }
+
+// terminals narrower than this get the compact layout
+const COMPACT_BELOW: u16 = 100;
+// terminals at least this wide get the extra columns
+const WIDE_FROM: u16 = 160;
+
+// the layout of the departures, chosen by the width of the terminal
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Breakpoint {
+    Compact, // no platform column and short line badges, i.e. an 80 column tmux pane
+    Normal,
+    Wide, // also planned time, delay, occupancy and messages
+}
+
+impl Breakpoint {
+    pub fn of(width: u16) -> Self {
+        if width < COMPACT_BELOW {
+            Breakpoint::Compact
+        } else if width < WIDE_FROM {
+            Breakpoint::Normal
+        } else {
+            Breakpoint::Wide
+        }
+    }
+
+    // narrow terminals use all of the space between the tabs and the status bar,
+    // wider ones a centered box
+    pub fn departures_area(self, body: Rect, screen: Rect) -> Rect {
+        match self {
+            Breakpoint::Compact => body,
+            Breakpoint::Normal => centered_rect(80, 69, screen),
+            Breakpoint::Wide => centered_rect(92, 69, screen),
+        }
+    }
+}
+
+// how far the lines of a scrolling box can be scrolled before the last one leaves the bottom
+pub fn max_scroll(lines: &[Line], area: Rect, wrap: bool) -> u16 {
+    let width = area.width.saturating_sub(SCROLL_BOX_WIDTH) as usize;
+    let height = area.height.saturating_sub(SCROLL_BOX_HEIGHT) as usize;
+    let content: usize = if wrap {
+        lines.iter().map(|line| wrapped_height(line, width)).sum()
+    } else {
+        lines.len()
+    };
+    content.saturating_sub(height).min(u16::MAX as usize) as u16
+}
+
+// the rows a line takes up when it is wrapped at the words, like Paragraph does with Wrap { trim: true }
+// words wider than the box are broken wherever they hit the edge
+fn wrapped_height(line: &Line, width: usize) -> usize {
+    let width = width.max(1);
+    let text: String = line
+        .spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect();
+    let mut rows = 1;
+    let mut used = 0;
+    for word in text.split_whitespace() {
+        let word_width = Span::raw(word).width();
+        if used == 0 && word_width <= width {
+            used = word_width;
+        } else if used > 0 && used + 1 + word_width <= width {
+            used += 1 + word_width;
+        } else {
+            if used > 0 {
+                rows += 1;
+            }
+            let extra_rows = word_width.saturating_sub(1) / width;
+            rows += extra_rows;
+            used = word_width - extra_rows * width;
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_that_dont_fit_move_to_the_next_row() {
+        assert_eq!(wrapped_height(&Line::from(""), 10), 1);
+        assert_eq!(wrapped_height(&Line::from("one two"), 10), 1);
+        assert_eq!(wrapped_height(&Line::from("one two three"), 10), 2);
+        assert_eq!(wrapped_height(&Line::from("one two three four"), 9), 3);
+    }
+
+    #[test]
+    fn long_words_are_broken_at_the_edge() {
+        assert_eq!(wrapped_height(&Line::from("abcdefghij"), 5), 2);
+        assert_eq!(wrapped_height(&Line::from("ab abcdefghijk"), 5), 4);
+    }
+
+    #[test]
+    fn the_box_shows_the_last_lines_at_the_bottom() {
+        let lines: Vec<Line> = (0..20).map(|i| Line::from(i.to_string())).collect();
+        // 10 rows without the border and the padding
+        assert_eq!(max_scroll(&lines, Rect::new(0, 0, 30, 14), false), 10);
+        assert_eq!(max_scroll(&lines[..5], Rect::new(0, 0, 30, 14), false), 0);
+    }
+}
@@ -1,44 +1,79 @@
+use std::collections::HashMap;
+
 use chrono::Utc;
 use ratatui::{
-    prelude::Constraint,
+    prelude::{Alignment, Constraint},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Cell, List, ListItem, Row, Table},
+    widgets::{block::Title, Block, Borders, Cell, List, ListItem, Row, Table},
 };
 
 use crate::{
-    api::{self, Station},
+    alarm::Alarm,
+    api::{self, TripKey},
+    app::{DepartureRow, StationListRow, TimeDisplay},
+    clock::format_time,
+    components::static_widgets::Breakpoint,
     constants::{get_sbahn_color, get_ubahn_color},
+    departure_diff::DepartureChange,
+    eta::Eta,
+    theme::Theme,
     App,
 };
 // this is used in the Station List tab
-pub fn get_station_list_widget(app: &App) -> List {
-    return List::new(
-        app.stations
-            .iter()
-            .map(|station| {
-                ListItem::new(vec![
-                    Line::from(vec![
-                        Span::styled(format!("{}", station.name), Style::default()),
-                        Span::styled(
-                            format!(" ({})", station.tariff_zones),
-                            Style::default().fg(Color::LightCyan),
-                        ),
-                    ]),
-                    Line::from(get_product_icon_spans(&station.products)),
-                ])
+// only the rows that fit into the height are turned into list items, starting at the offset
+pub fn get_station_list_widget(app: &App, offset: usize, height: usize) -> List<'_> {
+    let position = match app.scroll_state.selected() {
+        Some(i) => format!(" {}/{} ", i + 1, app.station_order.len()),
+        None => format!(" {} ", app.station_order.len()),
+    };
+
+    let mut used = 0;
+    let visible_rows = app.station_rows[offset.min(app.station_rows.len())..]
+        .iter()
+        .take_while(|row| {
+            used += row.height();
+            used <= height
+        });
+
+    List::new(
+        visible_rows
+            .map(|row| match row {
+                StationListRow::Header(title) => ListItem::new(Line::from(Span::styled(
+                    format!(" {} ", title),
+                    Style::default().fg(Color::Black).bg(app.theme.accent),
+                ))),
+                StationListRow::Station(i) => {
+                    let station = &app.stations[*i];
+                    ListItem::new(vec![
+                        Line::from(vec![
+                            Span::styled(station.name.as_str(), Style::default()),
+                            Span::styled(
+                                format!(" ({})", station.tariff_zones),
+                                Style::default().fg(app.theme.accent),
+                            ),
+                        ]),
+                        Line::from(get_product_icon_spans(&station.products)),
+                    ])
+                }
             })
             .collect::<Vec<ListItem>>(),
     )
+    .block(
+        Block::default()
+            .borders(Borders::TOP)
+            .title(format!(" Stations {} ", app.station_grouping.label()))
+            .title(Title::from(position).alignment(Alignment::Right)),
+    )
     .highlight_style(
         Style::default()
-            .bg(Color::Rgb(38, 35, 38))
+            .bg(app.theme.highlight)
             .add_modifier(Modifier::BOLD),
-    );
+    )
     // .highlight_symbol(">> ");
 }
 
-fn get_type_icon(product: &str) -> Span {
+fn get_type_icon(product: &str) -> Span<'_> {
     let icon = match product {
         "UBAHN" => Span::styled(
             " U ",
@@ -71,7 +106,7 @@ fn get_type_icon(product: &str) -> Span {
     icon
 }
 
-fn get_product_icon_spans(products: &Vec<String>) -> Vec<Span> {
+fn get_product_icon_spans(products: &Vec<String>) -> Vec<Span<'_>> {
     let mut spans = vec![];
     for product in products {
         let icon = get_type_icon(product);
@@ -81,43 +116,260 @@ fn get_product_icon_spans(products: &Vec<String>) -> Vec<Span> {
     spans
 }
 
-pub fn display_departures_table(departures: &[api::DepartureInfo]) -> Table {
-    let header_cells = ["Vehicle", "Direction", "Platform", "ETA"]
+// the header row plus its bottom margin, in terminal lines
+pub const DEPARTURE_HEADER_HEIGHT: u16 = 3;
+
+// how many departures a grouped row lists
+const GROUPED_ETAS: usize = 3;
+
+// every row shows the first departure of its group, grouped rows also list the next ETAs of the group
+// what the last refresh changed about a departure is highlighted for a moment
+pub fn display_departures_table<'a>(
+    departure_rows: &[DepartureRow<'a>],
+    theme: &Theme,
+    breakpoint: Breakpoint,
+    grouped: bool,
+    time_display: TimeDisplay,
+    alarms: &[Alarm],
+    changes: &HashMap<TripKey, DepartureChange>,
+) -> Table<'a> {
+    let header_names: &[&str] = match breakpoint {
+        Breakpoint::Compact => &["Line", "Direction", "ETA"],
+        Breakpoint::Normal => &["Vehicle", "Direction", "Platform", "ETA"],
+        Breakpoint::Wide => &[
+            "Vehicle",
+            "Direction",
+            "Platform",
+            "Planned",
+            "Delay",
+            "ETA",
+            "Occupancy",
+            "Messages",
+        ],
+    };
+    let header_cells = header_names
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Gray)));
+        .map(|h| Cell::from(*h).style(Style::default().fg(theme.muted)));
 
     let header = Row::new(header_cells)
         .style(
             Style::default(), // .bg(Color::White)
         )
-        .height(2)
+        .height(DEPARTURE_HEADER_HEIGHT - 1)
         .bottom_margin(1);
 
-    let rows = departures.iter().enumerate().map(|(index, item)| {
-        let cells = vec![
-            Cell::from(get_vehicle_label(&item.label, &item.transport_type)),
-            Cell::from(format!("{}", item.destination)),
-            Cell::from(get_platform_number(item.platform, index)),
-            Cell::from(match get_minutes(item.realtime_departure_time) {
-                ETA::Minutes(minutes) => format!("{} min", minutes),
-                ETA::Now => "now".to_string(),
-            }),
-        ];
-        return Row::new(cells).height(1);
+    let accent = theme.accent;
+    let changed = Style::default()
+        .fg(Color::LightYellow)
+        .add_modifier(Modifier::BOLD);
+    let muted = theme.muted;
+    let now = Utc::now().timestamp_millis();
+    // the platforms alternate in color within a stop point, the headers don't count
+    let mut position = 0;
+    let rows = departure_rows.iter().map(move |departure_row| {
+        let group = match departure_row {
+            DepartureRow::StopPoint(name) => {
+                position = 0;
+                return Row::new(vec![Cell::from(Span::styled(
+                    format!(" {} ", name),
+                    Style::default().fg(Color::Black).bg(accent),
+                ))])
+                .height(1);
+            }
+            DepartureRow::Departures(group) => group,
+        };
+        let index = position;
+        position += 1;
+        let item = group[0];
+        let has_alarm = group
+            .iter()
+            .any(|departure| alarms.iter().any(|alarm| alarm.matches(departure)));
+        let change = changes
+            .get(&item.trip_key())
+            .filter(|change| change.is_fresh());
+        let mut direction = Line::from(if has_alarm {
+            format!("⏰ {}", item.destination)
+        } else {
+            item.destination.to_string()
+        });
+        if change.is_some_and(|change| change.is_new) {
+            direction.spans.insert(0, Span::styled("new ", changed));
+        }
+        let mut eta = Cell::from(if grouped {
+            get_grouped_etas(group, time_display, now)
+        } else if breakpoint == Breakpoint::Normal {
+            // there is no planned column, a delayed departure shows both times
+            get_eta_with_planned(item, time_display, now)
+        } else {
+            get_eta(item, time_display, now)
+        });
+        let mut delay = Cell::from(get_delay(item.delay_in_minutes));
+        if change.is_some_and(|change| change.delay) {
+            eta = eta.style(changed);
+            delay = delay.style(changed);
+        }
+        let mut platform = get_platform_number(item.platform, index);
+        if change.is_some_and(|change| change.platform) {
+            platform.style = platform.style.bg(Color::LightYellow);
+        }
+        let cells = match breakpoint {
+            Breakpoint::Compact => vec![
+                Cell::from(get_line_badge(&item.label, &item.transport_type)),
+                Cell::from(direction),
+                eta,
+            ],
+            Breakpoint::Normal => vec![
+                Cell::from(get_vehicle_label(&item.label, &item.transport_type)),
+                Cell::from(direction),
+                Cell::from(platform),
+                eta,
+            ],
+            Breakpoint::Wide => vec![
+                Cell::from(get_vehicle_label(&item.label, &item.transport_type)),
+                Cell::from(direction),
+                Cell::from(platform),
+                Cell::from(format_time(item.planned_departure_time)),
+                delay,
+                eta,
+                Cell::from(get_occupancy(&item.occupancy)),
+                Cell::from(item.messages.join(" · ")),
+            ],
+        };
+        let row = Row::new(cells).height(1);
+        match change {
+            Some(change) if change.cancelled && item.cancelled => {
+                row.style(Style::default().fg(Color::LightRed))
+            }
+            Some(change) if change.cancelled => row.style(changed),
+            // gone until the next refresh drops it
+            _ if Eta::of(item, now) == Eta::Departed => row.style(Style::default().fg(muted)),
+            _ => row,
+        }
     });
 
-    let t = Table::new(rows)
-        .header(header)
-        .style(Style::default().fg(Color::White))
-        .widths(&[
+    // grouped rows need room for several ETAs
+    let widths: &'static [Constraint] = match breakpoint {
+        Breakpoint::Compact if grouped => &[
+            Constraint::Length(6),
+            Constraint::Min(10),
+            Constraint::Length(19),
+        ],
+        Breakpoint::Compact => &[
+            Constraint::Length(6),
+            Constraint::Min(10),
+            Constraint::Length(8),
+        ],
+        Breakpoint::Normal => &[
             Constraint::Percentage(20),
             Constraint::Max(50),
             Constraint::Percentage(20),
             Constraint::Min(10),
-        ]);
-    t
+        ],
+        Breakpoint::Wide if grouped => &[
+            Constraint::Length(14),
+            Constraint::Length(30),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(19),
+            Constraint::Length(10),
+            Constraint::Min(20),
+        ],
+        Breakpoint::Wide => &[
+            Constraint::Length(14),
+            Constraint::Length(30),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(10),
+            Constraint::Min(20),
+        ],
+    };
+
+    Table::new(rows)
+        .header(header)
+        .style(Style::default().fg(theme.text))
+        .highlight_style(
+            Style::default()
+                .bg(theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        )
+        .widths(widths)
+}
+
+fn get_eta(departure: &api::DepartureInfo, time_display: TimeDisplay, now: i64) -> String {
+    match time_display {
+        TimeDisplay::Clock => format_time(departure.realtime_departure_time),
+        TimeDisplay::Relative => Eta::of(departure, now).label(),
+    }
 }
 
+// i.e. "18:34 (18:30)" for a delayed departure
+fn get_eta_with_planned(
+    departure: &api::DepartureInfo,
+    time_display: TimeDisplay,
+    now: i64,
+) -> String {
+    let eta = get_eta(departure, time_display, now);
+    let planned = format_time(departure.planned_departure_time);
+    if time_display == TimeDisplay::Clock && eta != planned {
+        format!("{} ({})", eta, planned)
+    } else {
+        eta
+    }
+}
+
+// i.e. "now, 4, 11 min" or "18:32, 18:42, 18:52"
+fn get_grouped_etas(group: &[&api::DepartureInfo], time_display: TimeDisplay, now: i64) -> String {
+    if time_display == TimeDisplay::Clock {
+        let etas: Vec<String> = group
+            .iter()
+            .take(GROUPED_ETAS)
+            .map(|departure| get_eta(departure, time_display, now))
+            .collect();
+        return etas.join(", ");
+    }
+    let etas: Vec<Eta> = group
+        .iter()
+        .take(GROUPED_ETAS)
+        .map(|departure| Eta::of(departure, now))
+        .collect();
+    // only the last one says "min"
+    let labels: Vec<String> = etas
+        .iter()
+        .map(|eta| match eta {
+            Eta::Minutes(minutes) => minutes.to_string(),
+            eta => eta.label(),
+        })
+        .collect();
+    match etas.last() {
+        Some(Eta::Minutes(_)) => format!("{} min", labels.join(", ")),
+        _ => labels.join(", "),
+    }
+}
+
+fn get_delay(delay_in_minutes: Option<i64>) -> Span<'static> {
+    match delay_in_minutes {
+        Some(minutes) if minutes > 0 => Span::styled(
+            format!("+{}", minutes),
+            Style::default().fg(Color::LightRed),
+        ),
+        Some(minutes) if minutes < 0 => Span::raw(minutes.to_string()),
+        _ => Span::raw(""),
+    }
+}
+
+// the api reports "UNKNOWN" for most vehicles, that is left out
+fn get_occupancy(occupancy: &str) -> String {
+    match occupancy {
+        "UNKNOWN" | "" => String::new(),
+        occupancy => occupancy.to_lowercase(),
+    }
+}
+
+// is_multiple_of would need rust 1.87
+#[allow(clippy::manual_is_multiple_of)]
 fn get_platform_number<'a>(platform: Option<i64>, index: usize) -> Span<'a> {
     let bg = if index % 2 == 0 {
         Color::White
@@ -195,56 +447,47 @@ fn get_vehicle_label<'a>(label: &'a str, transport_type: &str) -> Line<'a> {
     Line::from(icon)
 }
 
-// sometimes, the departure time is negative
-// in that case, we return a string instead of a number. This is a temporary fix though
-enum ETA {
-    Minutes(i64),
-    Now, // it might also be depaurtures that are late and come in any moment. Needs more investigation
-}
-
-fn get_minutes(time: i64) -> ETA {
-    let now = Utc::now();
-    let timestamp_in_seconds = time / 1000;
-    let future_time = chrono::DateTime::from_timestamp(timestamp_in_seconds, 0).unwrap();
-    let diff = future_time.signed_duration_since(now); //now.signed_duration_since(future_time);
-
-    let minutes = diff.num_minutes();
-
-    if minutes < 1 {
-        return ETA::Now;
-    } else {
-        return ETA::Minutes(minutes);
-    }
+// only the colored line, without the product in front of it, for the compact layout
+pub fn get_line_badge<'a>(label: &'a str, transport_type: &str) -> Span<'a> {
+    let (bg, fg) = match transport_type {
+        "UBAHN" => (get_ubahn_color(label), Color::White),
+        "BUS" => (Color::Rgb(17, 93, 111), Color::White),
+        "TRAM" => (Color::Rgb(231, 27, 30), Color::White),
+        "SBAHN" => (get_sbahn_color(label), Color::White),
+        _ => (Color::LightYellow, Color::Black),
+    };
+    Span::styled(format!(" {} ", label), Style::default().bg(bg).fg(fg))
 }
 
 // search suggestions
 
-pub fn get_suggested_station_list(app: &mut App) -> List {
-    let mut suggested_stations: Vec<Station> = vec![];
-
+pub fn get_suggested_station_list(app: &App) -> List<'_> {
     let suggested_stations_list = app
-        .stations
+        .suggested_stations
         .iter()
-        .filter(|station| {
-            // todo: move to filter_map
-            station
-                .name
-                .to_ascii_lowercase()
-                .contains(&app.query.to_ascii_lowercase())
-        })
-        .map(|station| {
-            suggested_stations.push(station.clone());
-            return ListItem::new(vec![Line::from(vec![
-                Span::styled(format!("{}", station.name), Style::default()),
-                Span::styled(
-                    format!(" ({})", station.tariff_zones),
-                    Style::default().fg(Color::LightCyan),
-                ),
-            ])]);
+        .enumerate()
+        .map(|(position, i)| {
+            let station = &app.stations[*i];
+            let mut spans = vec![];
+            if position < app.favorite_suggestions {
+                spans.push(Span::styled("★ ", Style::default().fg(app.theme.accent)));
+            } else if position < app.favorite_suggestions + app.recent_suggestions {
+                spans.push(Span::styled("↺ ", Style::default().fg(app.theme.accent)));
+            }
+            spans.push(Span::styled(station.name.as_str(), Style::default()));
+            if app.station_index.is_ambiguous(*i) {
+                spans.push(Span::styled(
+                    format!(", {}", station.place),
+                    Style::default().fg(app.theme.muted),
+                ));
+            }
+            spans.push(Span::styled(
+                format!(" ({})", station.tariff_zones),
+                Style::default().fg(app.theme.accent),
+            ));
+            ListItem::new(vec![Line::from(spans)])
         })
         .collect::<Vec<ListItem>>();
 
-    app.suggested_stations = suggested_stations;
-
-    return List::new(suggested_stations_list);
+    List::new(suggested_stations_list)
 }
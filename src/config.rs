@@ -0,0 +1,97 @@
+use std::{collections::HashMap, fs::File};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+// stored next to stations.json, every field is optional
+const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    pub keymap: KeymapConfig,
+    pub alarm: AlarmConfig,
+    pub http: HttpConfig,
+}
+
+// "http": { "proxy": "http://proxy.example:8080", "connectTimeoutSeconds": 5, "timeoutSeconds": 15 }
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpConfig {
+    pub proxy: Option<String>, // overrides the proxy environment variables
+    pub connect_timeout_seconds: u64,
+    pub timeout_seconds: u64, // for the whole request, including reading the response
+}
+
+impl HttpConfig {
+    // reqwest would give up on every request right away with a zero timeout
+    fn validate(&self) -> Result<()> {
+        if self.connect_timeout_seconds == 0 {
+            bail!("http.connectTimeoutSeconds must be at least 1");
+        }
+        if self.timeout_seconds == 0 {
+            bail!("http.timeoutSeconds must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            connect_timeout_seconds: 5,
+            timeout_seconds: 15,
+        }
+    }
+}
+
+// "alarm": { "minutesBefore": 5, "command": "notify-send MVG \"$MVG_ALARM_MESSAGE\"" }
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AlarmConfig {
+    pub minutes_before: i64,     // used when no time is given with the command
+    pub command: Option<String>, // run by the shell when an alarm goes off
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        Self {
+            minutes_before: 5,
+            command: None,
+        }
+    }
+}
+
+// "keymap": { "preset": "vim", "bindings": { "normal": { "refresh": ["r", "f5"] } } }
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct KeymapConfig {
+    pub preset: String, // default, vim or emacs
+    pub bindings: HashMap<String, HashMap<String, Vec<String>>>, // mode => action => keys
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        Self {
+            preset: "default".to_string(),
+            bindings: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    // unlike the user data, a broken config is reported instead of silently ignored
+    pub fn load() -> Result<Self> {
+        let config: Self = match File::open(CONFIG_FILE) {
+            Ok(file) => serde_json::from_reader(file)
+                .with_context(|| format!("failed to read {}", CONFIG_FILE))?,
+            Err(_) => return Ok(Self::default()),
+        };
+        config
+            .http
+            .validate()
+            .with_context(|| format!("invalid {}", CONFIG_FILE))?;
+        Ok(config)
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::api::{DepartureInfo, TripKey};
+
+// how long a change stays highlighted after the refresh that brought it
+pub const HIGHLIGHT_DURATION: Duration = Duration::from_secs(10);
+
+// what a refresh changed about a trip
+#[derive(Debug, Clone, Copy)]
+pub struct DepartureChange {
+    pub is_new: bool, // not in the list before
+    pub delay: bool,
+    pub platform: bool,
+    pub cancelled: bool,
+    pub at: Instant,
+}
+
+impl DepartureChange {
+    pub fn is_fresh(&self) -> bool {
+        self.at.elapsed() < HIGHLIGHT_DURATION
+    }
+}
+
+// compares two lists of departures of the same station trip by trip
+// trips that are gone or didn't change are left out
+pub fn diff(old: &[DepartureInfo], new: &[DepartureInfo]) -> HashMap<TripKey, DepartureChange> {
+    let old: HashMap<TripKey, &DepartureInfo> = old
+        .iter()
+        .map(|departure| (departure.trip_key(), departure))
+        .collect();
+    let at = Instant::now();
+
+    new.iter()
+        .filter_map(|departure| {
+            let key = departure.trip_key();
+            let change = match old.get(&key) {
+                None => DepartureChange {
+                    is_new: true,
+                    delay: false,
+                    platform: false,
+                    cancelled: false,
+                    at,
+                },
+                Some(before) => DepartureChange {
+                    is_new: false,
+                    delay: before.delay_in_minutes.unwrap_or(0)
+                        != departure.delay_in_minutes.unwrap_or(0),
+                    platform: before.platform != departure.platform,
+                    cancelled: before.cancelled != departure.cancelled,
+                    at,
+                },
+            };
+            (change.is_new || change.delay || change.platform || change.cancelled)
+                .then_some((key, change))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn departure(label: &str, planned: i64) -> DepartureInfo {
+        DepartureInfo {
+            label: label.to_string(),
+            destination: "Messestadt Ost".to_string(),
+            planned_departure_time: planned,
+            realtime_departure_time: planned,
+            ..DepartureInfo::default()
+        }
+    }
+
+    #[test]
+    fn unchanged_trips_are_left_out() {
+        let old = vec![departure("U2", 1), departure("U2", 2)];
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn trips_that_weren_t_listed_before_are_new() {
+        let old = vec![departure("U2", 1)];
+        let new = vec![departure("U2", 1), departure("U2", 2)];
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[&new[1].trip_key()].is_new);
+    }
+
+    #[test]
+    fn gone_trips_are_left_out() {
+        let old = vec![departure("U2", 1), departure("U2", 2)];
+        assert!(diff(&old, &old[1..]).is_empty());
+    }
+
+    #[test]
+    fn reports_what_changed_about_a_trip() {
+        let old = vec![departure("U2", 1), departure("U7", 1), departure("U8", 1)];
+        let mut new = old.clone();
+        new[0].delay_in_minutes = Some(3);
+        new[1].platform = Some(2);
+        new[2].cancelled = true;
+        let changes = diff(&old, &new);
+
+        let delayed = changes[&new[0].trip_key()];
+        assert!(delayed.delay && !delayed.platform && !delayed.cancelled && !delayed.is_new);
+        let moved = changes[&new[1].trip_key()];
+        assert!(moved.platform && !moved.delay && !moved.cancelled);
+        let cancelled = changes[&new[2].trip_key()];
+        assert!(cancelled.cancelled && !cancelled.delay && !cancelled.platform);
+    }
+
+    #[test]
+    fn no_delay_and_a_delay_of_zero_are_the_same() {
+        let old = vec![departure("U2", 1)];
+        let mut new = old.clone();
+        new[0].delay_in_minutes = Some(0);
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn a_realtime_time_alone_is_not_a_new_trip() {
+        let old = vec![departure("U2", 1)];
+        let mut new = old.clone();
+        new[0].realtime_departure_time = 120_001;
+        assert!(diff(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::api::DepartureInfo;
+
+const MINUTE: i64 = 60_000;
+
+// how far the clock of this computer may be off from the one at MVG
+// a departure this close to its time isn't treated as gone yet
+pub const CLOCK_SKEW_TOLERANCE: i64 = MINUTE;
+
+// when a departure leaves, as seen from now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eta {
+    Departed,     // the realtime time has passed, the vehicle is gone
+    Boarding,     // realtime, leaves within the minute
+    Due,          // no realtime data, planned to leave within the minute
+    Minutes(i64), // whole minutes until it leaves, at least 1
+    Overdue(i64), // no realtime data and the planned time passed this many minutes ago, it might still come
+}
+
+impl Eta {
+    // now is a utc timestamp in milliseconds, like the times of the api
+    pub fn of(departure: &DepartureInfo, now: i64) -> Self {
+        Self::from_times(
+            departure.planned_departure_time,
+            departure.realtime_departure_time,
+            departure.realtime,
+            now,
+        )
+    }
+
+    // without realtime data, the realtime time is just the planned one and says nothing about the vehicle
+    pub fn from_times(planned: i64, realtime_time: i64, realtime: bool, now: i64) -> Self {
+        let time = if realtime { realtime_time } else { planned };
+        let until = time - now;
+
+        if until >= MINUTE {
+            Eta::Minutes(until / MINUTE)
+        } else if until >= -CLOCK_SKEW_TOLERANCE {
+            if realtime {
+                Eta::Boarding
+            } else {
+                Eta::Due
+            }
+        } else if realtime {
+            Eta::Departed
+        } else {
+            Eta::Overdue(-until / MINUTE)
+        }
+    }
+
+    // i.e. "4 min", or "+3 min" for three minutes overdue
+    pub fn label(self) -> String {
+        match self {
+            Eta::Departed => "departed".to_string(),
+            Eta::Boarding => "now".to_string(),
+            Eta::Due => "due".to_string(),
+            Eta::Minutes(minutes) => format!("{} min", minutes),
+            Eta::Overdue(minutes) => format!("+{} min", minutes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_760_800_000_000;
+
+    fn realtime(until: i64) -> Eta {
+        Eta::from_times(NOW, NOW + until, true, NOW)
+    }
+
+    fn planned(until: i64) -> Eta {
+        Eta::from_times(NOW + until, NOW + until, false, NOW)
+    }
+
+    #[test]
+    fn whole_minutes_are_rounded_down() {
+        assert_eq!(realtime(MINUTE), Eta::Minutes(1));
+        assert_eq!(realtime(2 * MINUTE - 1), Eta::Minutes(1));
+        assert_eq!(realtime(2 * MINUTE), Eta::Minutes(2));
+        assert_eq!(planned(10 * MINUTE + 30_000), Eta::Minutes(10));
+    }
+
+    #[test]
+    fn the_last_minute_is_boarding_with_realtime_and_due_without() {
+        assert_eq!(realtime(MINUTE - 1), Eta::Boarding);
+        assert_eq!(realtime(0), Eta::Boarding);
+        assert_eq!(planned(MINUTE - 1), Eta::Due);
+        assert_eq!(planned(0), Eta::Due);
+    }
+
+    #[test]
+    fn a_clock_that_runs_ahead_doesnt_make_departures_disappear() {
+        assert_eq!(realtime(-1), Eta::Boarding);
+        assert_eq!(realtime(-CLOCK_SKEW_TOLERANCE), Eta::Boarding);
+        assert_eq!(planned(-CLOCK_SKEW_TOLERANCE), Eta::Due);
+    }
+
+    #[test]
+    fn past_the_tolerance_realtime_departures_are_gone() {
+        assert_eq!(realtime(-CLOCK_SKEW_TOLERANCE - 1), Eta::Departed);
+        assert_eq!(realtime(-30 * MINUTE), Eta::Departed);
+    }
+
+    #[test]
+    fn past_the_tolerance_planned_departures_are_overdue() {
+        assert_eq!(planned(-CLOCK_SKEW_TOLERANCE - 1), Eta::Overdue(1));
+        assert_eq!(planned(-5 * MINUTE - 1), Eta::Overdue(5));
+    }
+
+    #[test]
+    fn overdue_departures_show_how_late_they_are() {
+        assert_eq!(Eta::Overdue(3).label(), "+3 min");
+        assert_eq!(Eta::Minutes(3).label(), "3 min");
+    }
+
+    #[test]
+    fn a_delay_keeps_a_departure_whose_planned_time_passed() {
+        // planned five minutes ago, but three minutes late according to realtime
+        let eta = Eta::from_times(NOW - 5 * MINUTE, NOW + 3 * MINUTE, true, NOW);
+        assert_eq!(eta, Eta::Minutes(3));
+    }
+
+    #[test]
+    fn without_realtime_the_realtime_time_is_ignored() {
+        let eta = Eta::from_times(NOW + 4 * MINUTE, NOW - 10 * MINUTE, false, NOW);
+        assert_eq!(eta, Eta::Minutes(4));
+    }
+}
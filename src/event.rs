@@ -1,5 +1,5 @@
 use anyhow::Result;
-use crossterm::event::{self, Event as CrosstermEvent, KeyEvent};
+use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, MouseEvent};
 use std::time::{Duration, Instant};
 
 /// reference: https://ratatui.rs/tutorial/counter-app/event.html
@@ -8,6 +8,11 @@ use std::time::{Duration, Instant};
 pub enum Event {
     Tick,
     Key(KeyEvent),
+    Mouse(MouseEvent),
+    Resize(u16, u16),
+    FocusGained,
+    FocusLost,
+    Refresh, // sent by the auto refresh task, see update::initiate_auto_refresh
 }
 
 #[derive(Debug)]
@@ -38,8 +43,14 @@ impl EventHandler {
                                     Ok(()) // ignore KeyEventKind::Release on windows
                                 }
                             }
+                            CrosstermEvent::Mouse(e) => sender.send(Event::Mouse(e)),
+                            CrosstermEvent::Resize(width, height) => {
+                                sender.send(Event::Resize(width, height))
+                            }
+                            CrosstermEvent::FocusGained => sender.send(Event::FocusGained),
+                            CrosstermEvent::FocusLost => sender.send(Event::FocusLost),
                             _ => {
-                                // ignore other events
+                                // ignore other events, i.e. pasted text
                                 Ok(())
                             }
                         }
@@ -0,0 +1,767 @@
+use std::{collections::HashMap, fmt};
+
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{app::AppMode, config::KeymapConfig};
+
+// everything a key can be bound to
+// the same action can mean slightly different things per mode, i.e. Up moves through the suggestions in search mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Search,
+    Refresh,
+    SwitchTab,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    Select,
+    GroupStations,
+    GroupDepartures,
+    FilterStopPoint,
+    Later,
+    Earlier,
+    TimeTravel,
+    ClockTimes,
+    Back,
+    CursorLeft,
+    CursorRight,
+    DeleteChar,
+    ClearHistory,
+    Help,
+    CommandPalette,
+    Complete,
+    Alerts,
+    FavoriteMessages,
+    Alarm,
+}
+
+impl Action {
+    // the name used in config.json
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Search => "search",
+            Action::Refresh => "refresh",
+            Action::SwitchTab => "switch_tab",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::Select => "select",
+            Action::GroupStations => "group_stations",
+            Action::GroupDepartures => "group_departures",
+            Action::FilterStopPoint => "filter_stop_point",
+            Action::Later => "later",
+            Action::Earlier => "earlier",
+            Action::TimeTravel => "time_travel",
+            Action::ClockTimes => "clock_times",
+            Action::Back => "back",
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::DeleteChar => "delete_char",
+            Action::ClearHistory => "clear_history",
+            Action::Help => "help",
+            Action::CommandPalette => "command_palette",
+            Action::Complete => "complete",
+            Action::Alerts => "alerts",
+            Action::FavoriteMessages => "favorite_messages",
+            Action::Alarm => "alarm",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "close app",
+            Action::Search => "search",
+            Action::Refresh => "reload departures",
+            Action::SwitchTab => "switch tabs",
+            Action::Up => "navigate up",
+            Action::Down => "navigate down",
+            Action::PageUp => "page up",
+            Action::PageDown => "page down",
+            Action::First => "first station",
+            Action::Last => "last station",
+            Action::Select => "select",
+            Action::GroupStations => "group stations",
+            Action::GroupDepartures => "group departures by line or stop point",
+            Action::FilterStopPoint => "only the selected stop point",
+            Action::Later => "look ahead to later departures",
+            Action::Earlier => "back towards now",
+            Action::TimeTravel => "departures at another time",
+            Action::ClockTimes => "clock times or minutes",
+            Action::Back => "back to normal mode",
+            Action::CursorLeft => "move cursor left",
+            Action::CursorRight => "move cursor right",
+            Action::DeleteChar => "delete character",
+            Action::ClearHistory => "clear history",
+            Action::Help => "show all keys",
+            Action::CommandPalette => "commands",
+            Action::Complete => "complete",
+            Action::Alerts => "show service alerts",
+            Action::FavoriteMessages => "only messages for favorite stations",
+            Action::Alarm => "notify before the departure leaves",
+        }
+    }
+
+    // the status bar only has room for the most important actions
+    fn in_status_bar(self) -> bool {
+        matches!(
+            self,
+            Action::Quit
+                | Action::Search
+                | Action::Refresh
+                | Action::SwitchTab
+                | Action::Select
+                | Action::GroupStations
+                | Action::Back
+                | Action::ClearHistory
+                | Action::Help
+                | Action::CommandPalette
+                | Action::Complete
+        )
+    }
+}
+
+// the actions available in every mode, in the order they are listed in the help
+const MODE_ACTIONS: [(AppMode, &[Action]); 6] = [
+    (
+        AppMode::Normal,
+        &[
+            Action::Quit,
+            Action::SwitchTab,
+            Action::Select,
+            Action::Refresh,
+            Action::Search,
+            Action::GroupStations,
+            Action::GroupDepartures,
+            Action::FilterStopPoint,
+            Action::Later,
+            Action::Earlier,
+            Action::TimeTravel,
+            Action::ClockTimes,
+            Action::Up,
+            Action::Down,
+            Action::PageUp,
+            Action::PageDown,
+            Action::First,
+            Action::Last,
+            Action::Help,
+            Action::CommandPalette,
+            Action::Alerts,
+            Action::FavoriteMessages,
+            Action::Alarm,
+        ],
+    ),
+    (
+        AppMode::Search,
+        &[
+            Action::Back,
+            Action::Select,
+            Action::Up,
+            Action::Down,
+            Action::CursorLeft,
+            Action::CursorRight,
+            Action::DeleteChar,
+            Action::ClearHistory,
+        ],
+    ),
+    (AppMode::Details, &[Action::Back, Action::Up, Action::Down]),
+    (AppMode::Help, &[Action::Back, Action::Up, Action::Down]),
+    (AppMode::Alerts, &[Action::Back, Action::Up, Action::Down]),
+    (
+        AppMode::Command,
+        &[
+            Action::Back,
+            Action::Select,
+            Action::Complete,
+            Action::Up,
+            Action::Down,
+            Action::CursorLeft,
+            Action::CursorRight,
+            Action::DeleteChar,
+        ],
+    ),
+];
+
+// every mode that has key bindings, in the order they are listed in the help
+pub fn modes() -> impl Iterator<Item = AppMode> {
+    MODE_ACTIONS.iter().map(|(mode, _)| *mode)
+}
+
+pub fn mode_name(mode: AppMode) -> &'static str {
+    match mode {
+        AppMode::Normal => "normal",
+        AppMode::Search => "search",
+        AppMode::Details => "details",
+        AppMode::Help => "help",
+        AppMode::Command => "command",
+        AppMode::Alerts => "alerts",
+    }
+}
+
+type PresetBindings = &'static [(AppMode, Action, &'static [&'static str])];
+
+const DEFAULT_PRESET: PresetBindings = &[
+    (AppMode::Normal, Action::Quit, &["q", "ctrl+c"]),
+    (AppMode::Normal, Action::SwitchTab, &["tab"]),
+    (AppMode::Normal, Action::Select, &["enter"]),
+    (AppMode::Normal, Action::Refresh, &["r"]),
+    (AppMode::Normal, Action::Search, &["s"]),
+    (AppMode::Normal, Action::GroupStations, &["o"]),
+    (AppMode::Normal, Action::GroupDepartures, &["v"]),
+    (AppMode::Normal, Action::FilterStopPoint, &["p"]),
+    (AppMode::Normal, Action::Later, &["]"]),
+    (AppMode::Normal, Action::Earlier, &["["]),
+    (AppMode::Normal, Action::TimeTravel, &["t"]),
+    (AppMode::Normal, Action::ClockTimes, &["c"]),
+    (AppMode::Normal, Action::Alerts, &["a"]),
+    (AppMode::Normal, Action::FavoriteMessages, &["f"]),
+    (AppMode::Normal, Action::Alarm, &["n"]),
+    (AppMode::Normal, Action::Up, &["up"]),
+    (AppMode::Normal, Action::Down, &["down"]),
+    (AppMode::Normal, Action::PageUp, &["pageup"]),
+    (AppMode::Normal, Action::PageDown, &["pagedown"]),
+    (AppMode::Normal, Action::First, &["home"]),
+    (AppMode::Normal, Action::Last, &["end"]),
+    (AppMode::Normal, Action::Help, &["?"]),
+    (AppMode::Normal, Action::CommandPalette, &[":"]),
+    (AppMode::Search, Action::Back, &["esc"]),
+    (AppMode::Search, Action::Select, &["enter"]),
+    (AppMode::Search, Action::Up, &["up"]),
+    (AppMode::Search, Action::Down, &["down"]),
+    (AppMode::Search, Action::CursorLeft, &["left"]),
+    (AppMode::Search, Action::CursorRight, &["right"]),
+    (AppMode::Search, Action::DeleteChar, &["backspace"]),
+    (AppMode::Search, Action::ClearHistory, &["ctrl+d"]),
+    (AppMode::Details, Action::Back, &["esc", "enter", "q"]),
+    (AppMode::Details, Action::Up, &["up"]),
+    (AppMode::Details, Action::Down, &["down"]),
+    (AppMode::Help, Action::Back, &["esc", "?", "q"]),
+    (AppMode::Help, Action::Up, &["up"]),
+    (AppMode::Help, Action::Down, &["down"]),
+    (AppMode::Alerts, Action::Back, &["esc", "a", "q"]),
+    (AppMode::Alerts, Action::Up, &["up"]),
+    (AppMode::Alerts, Action::Down, &["down"]),
+    (AppMode::Command, Action::Back, &["esc"]),
+    (AppMode::Command, Action::Select, &["enter"]),
+    (AppMode::Command, Action::Complete, &["tab"]),
+    (AppMode::Command, Action::Up, &["up"]),
+    (AppMode::Command, Action::Down, &["down"]),
+    (AppMode::Command, Action::CursorLeft, &["left"]),
+    (AppMode::Command, Action::CursorRight, &["right"]),
+    (AppMode::Command, Action::DeleteChar, &["backspace"]),
+];
+
+const VIM_PRESET: PresetBindings = &[
+    (AppMode::Normal, Action::Quit, &["q", "ctrl+c"]),
+    (AppMode::Normal, Action::SwitchTab, &["tab"]),
+    (AppMode::Normal, Action::Select, &["enter"]),
+    (AppMode::Normal, Action::Refresh, &["r"]),
+    (AppMode::Normal, Action::Search, &["/", "s"]),
+    (AppMode::Normal, Action::GroupStations, &["o"]),
+    (AppMode::Normal, Action::GroupDepartures, &["v"]),
+    (AppMode::Normal, Action::FilterStopPoint, &["p"]),
+    (AppMode::Normal, Action::Later, &["]"]),
+    (AppMode::Normal, Action::Earlier, &["["]),
+    (AppMode::Normal, Action::TimeTravel, &["t"]),
+    (AppMode::Normal, Action::ClockTimes, &["c"]),
+    (AppMode::Normal, Action::Alerts, &["a"]),
+    (AppMode::Normal, Action::FavoriteMessages, &["f"]),
+    (AppMode::Normal, Action::Alarm, &["n"]),
+    (AppMode::Normal, Action::Up, &["k", "up"]),
+    (AppMode::Normal, Action::Down, &["j", "down"]),
+    (AppMode::Normal, Action::PageUp, &["ctrl+u", "pageup"]),
+    (AppMode::Normal, Action::PageDown, &["ctrl+d", "pagedown"]),
+    (AppMode::Normal, Action::First, &["g", "home"]),
+    (AppMode::Normal, Action::Last, &["G", "end"]),
+    (AppMode::Normal, Action::Help, &["?"]),
+    (AppMode::Normal, Action::CommandPalette, &[":"]),
+    (AppMode::Search, Action::Back, &["esc"]),
+    (AppMode::Search, Action::Select, &["enter"]),
+    (AppMode::Search, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Search, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Search, Action::CursorLeft, &["left"]),
+    (AppMode::Search, Action::CursorRight, &["right"]),
+    (
+        AppMode::Search,
+        Action::DeleteChar,
+        &["backspace", "ctrl+h"],
+    ),
+    (AppMode::Search, Action::ClearHistory, &["ctrl+d"]),
+    (AppMode::Details, Action::Back, &["esc", "enter", "q"]),
+    (AppMode::Details, Action::Up, &["k", "up"]),
+    (AppMode::Details, Action::Down, &["j", "down"]),
+    (AppMode::Help, Action::Back, &["esc", "?", "q"]),
+    (AppMode::Help, Action::Up, &["k", "up"]),
+    (AppMode::Help, Action::Down, &["j", "down"]),
+    (AppMode::Alerts, Action::Back, &["esc", "a", "q"]),
+    (AppMode::Alerts, Action::Up, &["k", "up"]),
+    (AppMode::Alerts, Action::Down, &["j", "down"]),
+    (AppMode::Command, Action::Back, &["esc"]),
+    (AppMode::Command, Action::Select, &["enter"]),
+    (AppMode::Command, Action::Complete, &["tab"]),
+    (AppMode::Command, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Command, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Command, Action::CursorLeft, &["left"]),
+    (AppMode::Command, Action::CursorRight, &["right"]),
+    (
+        AppMode::Command,
+        Action::DeleteChar,
+        &["backspace", "ctrl+h"],
+    ),
+];
+
+const EMACS_PRESET: PresetBindings = &[
+    (AppMode::Normal, Action::Quit, &["q", "ctrl+c"]),
+    (AppMode::Normal, Action::SwitchTab, &["tab"]),
+    (AppMode::Normal, Action::Select, &["enter"]),
+    (AppMode::Normal, Action::Refresh, &["r"]),
+    (AppMode::Normal, Action::Search, &["ctrl+s", "s"]),
+    (AppMode::Normal, Action::GroupStations, &["o"]),
+    (AppMode::Normal, Action::GroupDepartures, &["v"]),
+    (AppMode::Normal, Action::FilterStopPoint, &["p"]),
+    (AppMode::Normal, Action::Later, &["]"]),
+    (AppMode::Normal, Action::Earlier, &["["]),
+    (AppMode::Normal, Action::TimeTravel, &["t"]),
+    (AppMode::Normal, Action::ClockTimes, &["c"]),
+    (AppMode::Normal, Action::Alerts, &["a"]),
+    (AppMode::Normal, Action::FavoriteMessages, &["f"]),
+    (AppMode::Normal, Action::Alarm, &["n"]),
+    (AppMode::Normal, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Normal, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Normal, Action::PageUp, &["alt+v", "pageup"]),
+    (AppMode::Normal, Action::PageDown, &["ctrl+v", "pagedown"]),
+    (AppMode::Normal, Action::First, &["alt+<", "home"]),
+    (AppMode::Normal, Action::Last, &["alt+>", "end"]),
+    (AppMode::Normal, Action::Help, &["?", "ctrl+h"]),
+    (AppMode::Normal, Action::CommandPalette, &[":", "alt+x"]),
+    (AppMode::Search, Action::Back, &["ctrl+g", "esc"]),
+    (AppMode::Search, Action::Select, &["enter"]),
+    (AppMode::Search, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Search, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Search, Action::CursorLeft, &["ctrl+b", "left"]),
+    (AppMode::Search, Action::CursorRight, &["ctrl+f", "right"]),
+    (
+        AppMode::Search,
+        Action::DeleteChar,
+        &["backspace", "ctrl+h"],
+    ),
+    (AppMode::Search, Action::ClearHistory, &["ctrl+d"]),
+    (
+        AppMode::Details,
+        Action::Back,
+        &["ctrl+g", "esc", "enter", "q"],
+    ),
+    (AppMode::Details, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Details, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Help, Action::Back, &["ctrl+g", "esc", "?", "q"]),
+    (AppMode::Help, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Help, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Alerts, Action::Back, &["ctrl+g", "esc", "a", "q"]),
+    (AppMode::Alerts, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Alerts, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Command, Action::Back, &["ctrl+g", "esc"]),
+    (AppMode::Command, Action::Select, &["enter"]),
+    (AppMode::Command, Action::Complete, &["tab"]),
+    (AppMode::Command, Action::Up, &["ctrl+p", "up"]),
+    (AppMode::Command, Action::Down, &["ctrl+n", "down"]),
+    (AppMode::Command, Action::CursorLeft, &["ctrl+b", "left"]),
+    (AppMode::Command, Action::CursorRight, &["ctrl+f", "right"]),
+    (
+        AppMode::Command,
+        Action::DeleteChar,
+        &["backspace", "ctrl+h"],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    // "q", "G", "ctrl+c", "alt+<", "pagedown", ...
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        // a "+" on its own is a key, not a separator
+        while let Some((modifier, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier '{}' in key '{}'", modifier, text),
+            };
+            rest = key;
+        }
+
+        let code = match rest.to_lowercase().as_str() {
+            "esc" => KeyCode::Esc,
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "space" => KeyCode::Char(' '),
+            name if name.len() > 1 && name.starts_with('f') => name[1..]
+                .parse()
+                .map(KeyCode::F)
+                .map_err(|_| anyhow!("unknown key '{}'", text))?,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => bail!("unknown key '{}'", text),
+                }
+            }
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+
+    // shift is part of the character itself, "G" is the same key as "shift+g"
+    // and caps lock doesn't change what ctrl+c means
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Self {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => Self {
+                code: KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    // a key that types text in the search field or the command palette
+    fn is_text(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && self.modifiers.is_empty()
+    }
+}
+
+impl From<&KeyEvent> for KeyBinding {
+    fn from(key_event: &KeyEvent) -> Self {
+        Self::new(key_event.code, key_event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if self.modifiers.is_empty() => write!(f, "{}", c),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "BackTab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Up => write!(f, "Up"),
+            KeyCode::Down => write!(f, "Down"),
+            KeyCode::Left => write!(f, "Left"),
+            KeyCode::Right => write!(f, "Right"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+// the active key bindings, the single source for key handling, the status bar and the help
+pub struct Keymap {
+    actions: HashMap<(AppMode, KeyBinding), Action>,
+    keys: HashMap<(AppMode, Action), Vec<KeyBinding>>,
+}
+
+impl Keymap {
+    // starts from the preset and replaces the keys of every action listed in the config
+    // two actions sharing a key in the same mode is an error, so the app refuses to start with an ambiguous keymap
+    pub fn new(config: &KeymapConfig) -> Result<Self> {
+        let preset = match config.preset.as_str() {
+            "default" => DEFAULT_PRESET,
+            "vim" => VIM_PRESET,
+            "emacs" => EMACS_PRESET,
+            other => bail!(
+                "unknown keymap preset '{}', choose default, vim or emacs",
+                other
+            ),
+        };
+
+        let mut keys: HashMap<(AppMode, Action), Vec<KeyBinding>> = HashMap::new();
+        for (mode, action, bindings) in preset {
+            let bindings = bindings
+                .iter()
+                .map(|key| KeyBinding::parse(key))
+                .collect::<Result<Vec<_>>>()?;
+            keys.insert((*mode, *action), bindings);
+        }
+
+        for (mode_name, overrides) in &config.bindings {
+            let (mode, actions) = MODE_ACTIONS
+                .iter()
+                .find(|(mode, _)| self::mode_name(*mode) == mode_name)
+                .ok_or_else(|| anyhow!("unknown mode '{}' in the keymap", mode_name))?;
+            for (action_name, bindings) in overrides {
+                let action = actions
+                    .iter()
+                    .find(|action| action.name() == action_name)
+                    .ok_or_else(|| {
+                        anyhow!("unknown action '{}' in {} mode", action_name, mode_name)
+                    })?;
+                let bindings = bindings
+                    .iter()
+                    .map(|key| KeyBinding::parse(key))
+                    .collect::<Result<Vec<_>>>()?;
+                keys.insert((*mode, *action), bindings);
+            }
+        }
+
+        let mut actions = HashMap::new();
+        for ((mode, action), bindings) in &keys {
+            for key in bindings {
+                if matches!(mode, AppMode::Search | AppMode::Command) && key.is_text() {
+                    bail!(
+                        "'{}' can't be bound to '{}' in {} mode, it is needed for typing",
+                        key,
+                        action.name(),
+                        mode_name(*mode)
+                    );
+                }
+                if let Some(other) = actions.insert((*mode, *key), *action) {
+                    if other != *action {
+                        bail!(
+                            "key '{}' is bound to both '{}' and '{}' in {} mode",
+                            key,
+                            other.name(),
+                            action.name(),
+                            mode_name(*mode)
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(Self { actions, keys })
+    }
+
+    pub fn action(&self, mode: AppMode, key_event: &KeyEvent) -> Option<Action> {
+        self.actions
+            .get(&(mode, KeyBinding::from(key_event)))
+            .copied()
+    }
+
+    pub fn keys(&self, mode: AppMode, action: Action) -> &[KeyBinding] {
+        self.keys
+            .get(&(mode, action))
+            .map_or(&[], |keys| keys.as_slice())
+    }
+
+    // every action of the mode with its keys, in a stable order
+    pub fn bindings(&self, mode: AppMode) -> Vec<(Action, &[KeyBinding])> {
+        MODE_ACTIONS
+            .iter()
+            .filter(|(m, _)| *m == mode)
+            .flat_map(|(_, actions)| actions.iter())
+            .map(|action| (*action, self.keys(mode, *action)))
+            .collect()
+    }
+
+    // i.e. " q: close app. Tab: switch tabs. "
+    pub fn status_help(&self, mode: AppMode) -> String {
+        let mut help = String::from(" ");
+        for (action, keys) in self.bindings(mode) {
+            if let (true, Some(key)) = (action.in_status_bar(), keys.first()) {
+                help.push_str(&format!("{}: {}. ", key, action.description()));
+            }
+        }
+        help
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(preset: &str, bindings: &[(&str, &str, &[&str])]) -> KeymapConfig {
+        let mut config = KeymapConfig {
+            preset: preset.to_string(),
+            bindings: HashMap::new(),
+        };
+        for (mode, action, keys) in bindings {
+            config.bindings.entry(mode.to_string()).or_default().insert(
+                action.to_string(),
+                keys.iter().map(|key| key.to_string()).collect(),
+            );
+        }
+        config
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        assert_eq!(
+            KeyBinding::parse("ctrl+c").unwrap(),
+            KeyBinding::new(KeyCode::Char('c'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeyBinding::parse("Ctrl+Alt+x").unwrap(),
+            KeyBinding::new(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(
+            KeyBinding::parse("pagedown").unwrap(),
+            KeyBinding::new(KeyCode::PageDown, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("f5").unwrap(),
+            KeyBinding::new(KeyCode::F(5), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("space").unwrap(),
+            KeyBinding::new(KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn a_plus_on_its_own_is_a_key() {
+        assert_eq!(
+            KeyBinding::parse("+").unwrap(),
+            KeyBinding::new(KeyCode::Char('+'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("ctrl++").unwrap(),
+            KeyBinding::new(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn shift_is_part_of_the_character() {
+        let upper = KeyBinding::parse("A").unwrap();
+        assert_eq!(KeyBinding::parse("shift+a").unwrap(), upper);
+        assert_ne!(KeyBinding::parse("a").unwrap(), upper);
+        // terminals send "A" with or without the shift modifier
+        assert_eq!(
+            KeyBinding::from(&key(KeyCode::Char('A'), KeyModifiers::SHIFT)),
+            upper
+        );
+        assert_eq!(
+            KeyBinding::from(&key(KeyCode::Char('A'), KeyModifiers::NONE)),
+            upper
+        );
+    }
+
+    #[test]
+    fn ctrl_ignores_caps_lock() {
+        assert_eq!(
+            KeyBinding::from(&key(KeyCode::Char('C'), KeyModifiers::CONTROL)),
+            KeyBinding::parse("ctrl+c").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert!(KeyBinding::parse("foo").is_err());
+        assert!(KeyBinding::parse("fx").is_err());
+        assert!(KeyBinding::parse("hyper+a").is_err());
+        assert!(KeyBinding::parse("").is_err());
+    }
+
+    #[test]
+    fn every_preset_loads() {
+        for preset in ["default", "vim", "emacs"] {
+            assert!(Keymap::new(&config(preset, &[])).is_ok(), "{}", preset);
+        }
+        assert!(Keymap::new(&config("nano", &[])).is_err());
+    }
+
+    #[test]
+    fn overrides_replace_the_keys_of_an_action() {
+        let keymap = Keymap::new(&config("default", &[("normal", "refresh", &["f5"])])).unwrap();
+        assert_eq!(
+            keymap.action(AppMode::Normal, &key(KeyCode::F(5), KeyModifiers::NONE)),
+            Some(Action::Refresh)
+        );
+        assert_eq!(
+            keymap.action(
+                AppMode::Normal,
+                &key(KeyCode::Char('r'), KeyModifiers::NONE)
+            ),
+            None
+        );
+        // the other actions keep their preset keys
+        assert_eq!(
+            keymap.action(
+                AppMode::Normal,
+                &key(KeyCode::Char('q'), KeyModifiers::NONE)
+            ),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_bindings() {
+        let conflict = config("default", &[("normal", "refresh", &["q"])]);
+        let error = Keymap::new(&conflict).err().unwrap().to_string();
+        assert!(
+            error.contains("'quit'") && error.contains("'refresh'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn the_same_key_may_be_used_in_different_modes() {
+        let keymap = Keymap::new(&config("default", &[("details", "back", &["r"])]));
+        assert!(keymap.is_ok());
+    }
+
+    #[test]
+    fn typing_keys_stay_free_in_search_and_command_mode() {
+        assert!(Keymap::new(&config("default", &[("search", "back", &["x"])])).is_err());
+        assert!(Keymap::new(&config("default", &[("command", "back", &["ctrl+x"])])).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_modes_and_actions() {
+        assert!(Keymap::new(&config("default", &[("visual", "quit", &["q"])])).is_err());
+        assert!(Keymap::new(&config("default", &[("normal", "fly", &["x"])])).is_err());
+        // an action that exists, but not in that mode
+        assert!(Keymap::new(&config("default", &[("help", "search", &["x"])])).is_err());
+    }
+}
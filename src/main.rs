@@ -1,20 +1,39 @@
+// the code base writes explicit returns and format! for plain strings
+#![allow(clippy::needless_return, clippy::useless_format)]
+
+use std::time::Duration;
+
 use anyhow::Result; //to avoid writing the error type <Box dyn Error> everywhere
 
+pub mod alarm;
+pub mod alerts;
 pub mod api;
 pub mod app;
+pub mod cli;
+pub mod clock;
+pub mod commands;
 pub mod components;
+pub mod config;
 pub mod constants;
+pub mod departure_diff;
+pub mod eta;
 pub mod event;
+pub mod keymap;
+#[cfg(feature = "offline-stations")]
+pub mod snapshot;
+pub mod station_index;
+pub mod theme;
 pub mod tui;
 pub mod ui;
 pub mod update;
+pub mod user_data;
 
 //own modules
 
 use app::App;
 use event::{Event, EventHandler};
 use tui::Tui;
-use update::update;
+use update::{handle_mouse, update};
 
 use ratatui::prelude::{CrosstermBackend, Terminal};
 
@@ -24,17 +43,36 @@ pub type Frame<'a> = ratatui::Frame<'a, CrosstermBackend<std::io::Stderr>>; // a
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = cli::Args::parse()?;
+
     println!("fetching stations...");
 
+    // a broken config stops the app here, before the terminal is taken over
+    let config = config::Config::load()?;
+    let keymap = keymap::Keymap::new(&config.keymap)?;
+
+    let http = api::build_client(
+        config.http.proxy.as_deref(),
+        Duration::from_secs(config.http.connect_timeout_seconds),
+        Duration::from_secs(config.http.timeout_seconds),
+    )?;
+
+    let mut app = App::new(keymap, config.alarm, http).await;
+    app.departures_from = args.at;
+
+    if let Some(station) = &args.station {
+        // this may prompt on stdin, so it has to happen before the event handler starts reading the terminal
+        let i = cli::resolve_station(&app.stations, &app.station_index, station)?;
+        app.open_station(i).await;
+    }
+
     let backend = CrosstermBackend::new(std::io::stderr());
     let terminal = Terminal::new(backend)?;
     let events = EventHandler::new(250);
 
     let sender = events.sender.clone(); //we can clone it as we can have multiple senders for this channel
 
-    let mut app = App::new().await;
-
-    initiate_auto_refresh(sender);
+    initiate_auto_refresh(sender, app.refresh_failures.subscribe());
 
     let mut tui = Tui::new(terminal, events);
     tui.enter()?;
@@ -47,8 +85,13 @@ async fn main() -> Result<()> {
         }
 
         match tui.events.next().await? {
-            Event::Tick => {} //every 250ms we get a tick event, we ignore it
+            Event::Tick => app.tick(), //every 250ms, only used to scroll the alerts ticker
             Event::Key(key_event) => update(&mut app, key_event).await,
+            Event::Mouse(mouse_event) => handle_mouse(&mut app, mouse_event).await,
+            Event::Resize(_, _) => app.should_redraw = true, // the terminal resizes its buffers on the next draw
+            Event::FocusGained => app.focus_gained().await,
+            Event::FocusLost => app.focus_lost(),
+            Event::Refresh => app.auto_refresh().await,
         };
     }
 
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use crate::api::Station;
+
+// the station list has a few thousand entries, so it is built once at startup
+// and every lookup returns indices into the original Vec<Station> instead of cloning stations
+pub struct StationIndex {
+    len: usize,
+    by_id: HashMap<String, usize>,
+    by_diva_id: HashMap<i64, usize>,
+    by_abbreviation: HashMap<String, usize>, // keys are uppercase, "KA"
+    lowercase_names: Vec<String>,
+    lowercase_places: Vec<String>,
+    ambiguous: Vec<bool>, // the same name exists in more than one place, e.g. "Bahnhof"
+    sorted_names: Vec<usize>, // station indices ordered by lowercase name, for prefix search
+    trigrams: HashMap<[char; 3], Vec<usize>>, // every posting list is sorted ascending
+    by_product: HashMap<String, Vec<usize>>, // "UBAHN" => [..]
+    by_zone: HashMap<String, Vec<usize>>, // "m" => [..], a station in "m|1" is in both zones
+}
+
+impl StationIndex {
+    pub fn new(stations: &[Station]) -> Self {
+        let mut index = Self {
+            len: stations.len(),
+            by_id: HashMap::with_capacity(stations.len()),
+            by_diva_id: HashMap::with_capacity(stations.len()),
+            by_abbreviation: HashMap::new(),
+            lowercase_names: Vec::with_capacity(stations.len()),
+            lowercase_places: Vec::with_capacity(stations.len()),
+            ambiguous: vec![false; stations.len()],
+            sorted_names: (0..stations.len()).collect(),
+            trigrams: HashMap::new(),
+            by_product: HashMap::new(),
+            by_zone: HashMap::new(),
+        };
+
+        for (i, station) in stations.iter().enumerate() {
+            index.by_id.insert(station.id.clone(), i);
+            index.by_diva_id.insert(station.diva_id, i);
+            if let Some(abbreviation) = &station.abbreviation {
+                index
+                    .by_abbreviation
+                    .entry(abbreviation.to_uppercase())
+                    .or_insert(i); // first one wins, abbreviations are not guaranteed to be unique
+            }
+
+            let name = station.name.to_lowercase();
+            for trigram in trigrams_of(&name) {
+                let postings = index.trigrams.entry(trigram).or_default();
+                // a name can contain the same trigram twice
+                if postings.last() != Some(&i) {
+                    postings.push(i);
+                }
+            }
+            index.lowercase_names.push(name);
+            index.lowercase_places.push(station.place.to_lowercase());
+
+            for product in &station.products {
+                index.by_product.entry(product.clone()).or_default().push(i);
+            }
+            for zone in station.tariff_zones.split('|') {
+                index.by_zone.entry(zone.to_string()).or_default().push(i);
+            }
+        }
+
+        let names = &index.lowercase_names;
+        index.sorted_names.sort_by(|a, b| names[*a].cmp(&names[*b]));
+
+        // equal names are neighbours now, so one pass finds the names shared across places
+        for pair in index.sorted_names.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if names[a] == names[b] && index.lowercase_places[a] != index.lowercase_places[b] {
+                index.ambiguous[a] = true;
+                index.ambiguous[b] = true;
+            }
+        }
+
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<usize> {
+        self.by_id.get(id).copied()
+    }
+
+    pub fn by_diva_id(&self, diva_id: i64) -> Option<usize> {
+        self.by_diva_id.get(&diva_id).copied()
+    }
+
+    pub fn by_abbreviation(&self, abbreviation: &str) -> Option<usize> {
+        self.by_abbreviation
+            .get(&abbreviation.to_uppercase())
+            .copied()
+    }
+
+    // lowercase name of the station, so callers don't have to allocate to compare names
+    pub fn lowercase_name(&self, i: usize) -> &str {
+        &self.lowercase_names[i]
+    }
+
+    // true if the name alone doesn't tell which station it is, so the place should be shown next to it
+    pub fn is_ambiguous(&self, i: usize) -> bool {
+        self.ambiguous[i]
+    }
+
+    // stations whose name starts with the prefix, in alphabetical order
+    pub fn prefix_search(&self, prefix: &str) -> Vec<usize> {
+        let prefix = prefix.to_lowercase();
+        let names = &self.lowercase_names;
+        let start = self
+            .sorted_names
+            .partition_point(|i| names[*i].as_str() < prefix.as_str());
+
+        self.sorted_names[start..]
+            .iter()
+            .take_while(|i| names[**i].starts_with(&prefix))
+            .copied()
+            .collect()
+    }
+
+    // stations whose name contains the query, in the original order of the station list
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return (0..self.len).collect();
+        }
+
+        let query_trigrams = trigrams_of(&query);
+        if query_trigrams.is_empty() {
+            // one or two characters are too short for the trigram index, but a linear scan without clones is cheap
+            return self.matching(0..self.len, &query);
+        }
+
+        // the rarest trigram gives the smallest candidate set
+        let rarest = query_trigrams
+            .iter()
+            .map(|trigram| self.trigrams.get(trigram).map_or(&[][..], |p| p.as_slice()))
+            .min_by_key(|postings| postings.len())
+            .unwrap_or(&[]);
+
+        self.matching(rarest.iter().copied(), &query)
+    }
+
+    pub fn with_product(&self, product: &str) -> &[usize] {
+        self.by_product
+            .get(&product.to_uppercase())
+            .map_or(&[], |stations| stations.as_slice())
+    }
+
+    pub fn in_zone(&self, zone: &str) -> &[usize] {
+        self.by_zone
+            .get(&zone.to_lowercase())
+            .map_or(&[], |stations| stations.as_slice())
+    }
+
+    // best guess for a station given by the user outside of the search modal:
+    // an exact id, an exact name, an abbreviation, and finally every name containing the text
+    pub fn resolve(&self, text: &str) -> Vec<usize> {
+        let text = text.trim();
+        if let Some(i) = self.by_id(text) {
+            return vec![i];
+        }
+
+        let name = text.to_lowercase();
+        let exact_names: Vec<usize> = self
+            .prefix_search(&name)
+            .into_iter()
+            .filter(|i| self.lowercase_names[*i] == name)
+            .collect();
+        if !exact_names.is_empty() {
+            return exact_names;
+        }
+
+        if let Some(i) = self.by_abbreviation(text) {
+            return vec![i];
+        }
+
+        self.search(text)
+    }
+
+    // the name part of the query narrowed down by its qualifiers, in the original order of the station list
+    pub fn query(&self, query: &SearchQuery) -> Vec<usize> {
+        let mut stations = self.search(&query.name);
+        for zone in &query.zones {
+            let in_zone = self.in_zone(zone);
+            stations.retain(|i| in_zone.binary_search(i).is_ok());
+        }
+        for product in &query.products {
+            let with_product = self.with_product(product);
+            stations.retain(|i| with_product.binary_search(i).is_ok());
+        }
+        for place in &query.places {
+            let place = place.to_lowercase();
+            stations.retain(|i| self.lowercase_places[*i].contains(&place));
+        }
+        stations
+    }
+
+    fn matching(&self, candidates: impl Iterator<Item = usize>, query: &str) -> Vec<usize> {
+        candidates
+            .filter(|i| self.lowercase_names[*i].contains(query))
+            .collect()
+    }
+}
+
+fn trigrams_of(text: &str) -> Vec<[char; 3]> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+// a search like "bahnhof place:freising has:sbahn zone:1"
+// qualifiers are matched case-insensitively and combined with AND, everything else is the name
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchQuery<'a> {
+    pub name: String,
+    pub zones: Vec<&'a str>,
+    pub places: Vec<&'a str>,
+    pub products: Vec<&'a str>, // "ubahn", "sbahn", "tram", "bus", "bahn"
+}
+
+impl<'a> SearchQuery<'a> {
+    pub fn parse(query: &'a str) -> Self {
+        let mut parsed = Self::default();
+        let mut name_parts = vec![];
+
+        for word in query.split_whitespace() {
+            let qualifier = word
+                .split_once(':')
+                .map(|(key, value)| (key.to_lowercase(), value));
+            match qualifier
+                .as_ref()
+                .map(|(key, value)| (key.as_str(), *value))
+            {
+                // a qualifier without a value is ignored while it is being typed
+                Some(("zone" | "place" | "has", "")) => {}
+                Some(("zone", zone)) => parsed.zones.push(zone),
+                Some(("place", place)) => parsed.places.push(place),
+                Some(("has", product)) => parsed.products.push(product),
+                _ => name_parts.push(word),
+            }
+        }
+
+        parsed.name = name_parts.join(" ");
+        parsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn station(id: &str, name: &str, place: &str, zones: &str, products: &[&str]) -> Station {
+        Station {
+            name: name.to_string(),
+            place: place.to_string(),
+            id: id.to_string(),
+            diva_id: id.len() as i64,
+            abbreviation: None,
+            tariff_zones: zones.to_string(),
+            products: products.iter().map(|product| product.to_string()).collect(),
+            latitude: 0.0,
+            longitude: 0.0,
+        }
+    }
+
+    fn stations() -> Vec<Station> {
+        vec![
+            station(
+                "de:09162:6",
+                "Hauptbahnhof",
+                "München",
+                "m",
+                &["UBAHN", "SBAHN"],
+            ),
+            station("de:09178:1", "Bahnhof", "Freising", "3", &["SBAHN", "BUS"]),
+            station("de:09174:1", "Bahnhof", "Dachau", "1|2", &["SBAHN"]),
+            station(
+                "de:09162:2",
+                "Marienplatz",
+                "München",
+                "m",
+                &["UBAHN", "SBAHN"],
+            ),
+        ]
+    }
+
+    #[test]
+    fn search_finds_names_containing_the_query_in_list_order() {
+        let stations = stations();
+        let index = StationIndex::new(&stations);
+        assert_eq!(index.search("BAHNHOF"), [0, 1, 2]);
+        assert_eq!(index.search("platz"), [3]);
+        // too short for the trigram index
+        assert_eq!(index.search("ma"), [3]);
+        assert_eq!(index.search(""), [0, 1, 2, 3]);
+        assert!(index.search("xyz").is_empty());
+    }
+
+    #[test]
+    fn prefix_search_is_alphabetical() {
+        let stations = stations();
+        let index = StationIndex::new(&stations);
+        assert_eq!(index.prefix_search("Ba"), [1, 2]);
+        assert_eq!(index.prefix_search("h"), [0]);
+        assert!(index.prefix_search("z").is_empty());
+    }
+
+    #[test]
+    fn names_shared_across_places_are_ambiguous() {
+        let stations = stations();
+        let index = StationIndex::new(&stations);
+        assert!(index.is_ambiguous(1) && index.is_ambiguous(2));
+        assert!(!index.is_ambiguous(0));
+    }
+
+    #[test]
+    fn resolve_prefers_ids_then_exact_names_then_abbreviations() {
+        let mut stations = stations();
+        stations[0].abbreviation = Some("HBF".to_string());
+        stations[3].abbreviation = Some("MP".to_string());
+        let index = StationIndex::new(&stations);
+        assert_eq!(index.resolve("de:09162:2"), [3]);
+        assert_eq!(index.resolve(" marienplatz "), [3]);
+        assert_eq!(index.resolve("Bahnhof"), [1, 2]);
+        assert_eq!(index.resolve("hbf"), [0]);
+        assert_eq!(index.resolve("haupt"), [0]);
+        assert!(index.resolve("nowhere").is_empty());
+    }
+
+    #[test]
+    fn parses_qualifiers_and_keeps_the_rest_as_name() {
+        let query = SearchQuery::parse("bahnhof place:freising has:sbahn zone:3");
+        assert_eq!(query.name, "bahnhof");
+        assert_eq!(query.places, ["freising"]);
+        assert_eq!(query.products, ["sbahn"]);
+        assert_eq!(query.zones, ["3"]);
+    }
+
+    #[test]
+    fn qualifier_keys_ignore_case() {
+        let query = SearchQuery::parse("Zone:M HAS:ubahn Place:München");
+        assert_eq!(query.name, "");
+        assert_eq!(query.zones, ["M"]);
+        assert_eq!(query.products, ["ubahn"]);
+        assert_eq!(query.places, ["München"]);
+    }
+
+    #[test]
+    fn a_qualifier_without_a_value_is_ignored() {
+        let query = SearchQuery::parse("marien zone:");
+        assert_eq!(
+            query,
+            SearchQuery {
+                name: "marien".to_string(),
+                ..SearchQuery::default()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_qualifiers_are_part_of_the_name() {
+        assert_eq!(SearchQuery::parse("line:u6").name, "line:u6");
+    }
+
+    #[test]
+    fn qualifiers_narrow_down_the_name_search() {
+        let stations = stations();
+        let index = StationIndex::new(&stations);
+        assert_eq!(index.query(&SearchQuery::parse("bahnhof")), [0, 1, 2]);
+        assert_eq!(
+            index.query(&SearchQuery::parse("bahnhof place:freising")),
+            [1]
+        );
+        assert_eq!(index.query(&SearchQuery::parse("ZONE:M has:UBAHN")), [0, 3]);
+        assert_eq!(index.query(&SearchQuery::parse("zone:2")), [2]);
+        assert!(index
+            .query(&SearchQuery::parse("marien has:bus"))
+            .is_empty());
+    }
+}
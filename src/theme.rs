@@ -0,0 +1,62 @@
+use ratatui::style::Color;
+
+// the colors of the app chrome, the vehicle colors in constants.rs stay the same in every theme
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub accent: Color,       // titles, zones and headings
+    pub selected_tab: Color, // the title of the open tab
+    pub highlight: Color,    // background of the selected row
+    pub text: Color,
+    pub muted: Color, // table headers and secondary information
+}
+
+pub const THEMES: [Theme; 4] = [
+    Theme {
+        name: "default",
+        accent: Color::LightCyan,
+        selected_tab: Color::Green,
+        highlight: Color::Rgb(38, 35, 53),
+        text: Color::White,
+        muted: Color::Gray,
+    },
+    Theme {
+        name: "light", // for terminals with a light background
+        accent: Color::Blue,
+        selected_tab: Color::Magenta,
+        highlight: Color::Rgb(215, 215, 230),
+        text: Color::Black,
+        muted: Color::DarkGray,
+    },
+    Theme {
+        name: "high-contrast",
+        accent: Color::Yellow,
+        selected_tab: Color::LightYellow,
+        highlight: Color::Blue,
+        text: Color::White,
+        muted: Color::White,
+    },
+    Theme {
+        name: "mono",
+        accent: Color::White,
+        selected_tab: Color::White,
+        highlight: Color::DarkGray,
+        text: Color::White,
+        muted: Color::Gray,
+    },
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        THEMES[0]
+    }
+}
+
+impl Theme {
+    pub fn by_name(name: &str) -> Option<Theme> {
+        THEMES
+            .iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(name))
+            .copied()
+    }
+}
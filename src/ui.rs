@@ -1,26 +1,39 @@
+use std::ops::Range;
+
 use ratatui::{
-    prelude::{Constraint, Direction, Layout},
+    prelude::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, Padding, Paragraph, Tabs},
+    widgets::{Block, Borders, Clear, ListState, Padding, Paragraph, Tabs},
 };
 
 use crate::{
-    app::{App, AppTabs},
+    app::{App, AppTabs, DepartureGrouping},
+    clock::format_day_time,
     components::{
-        self, static_widgets,
-        station_list::{display_departures_table, get_suggested_station_list},
+        self,
+        alerts::{alerts_max_scroll, get_alerts_widget, get_ticker_widget},
+        command_palette::get_command_suggestion_list,
+        departure_details::get_departure_details_widget,
+        help::{get_help_widget, help_max_scroll},
+        messages::{get_messages_widget, messages_max_scroll},
+        static_widgets::{self, Breakpoint},
+        station_list::{
+            display_departures_table, get_suggested_station_list, DEPARTURE_HEADER_HEIGHT,
+        },
     },
     tui::Frame,
 };
 
 pub fn render(app: &mut App, f: &mut Frame) {
     let size = f.size();
+    let ticker_height = if app.alerts.is_empty() { 0 } else { 1 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
             Constraint::Min(0),
+            Constraint::Length(ticker_height),
             Constraint::Length(1),
         ])
         .split(size);
@@ -28,12 +41,16 @@ pub fn render(app: &mut App, f: &mut Frame) {
     let block = Block::default();
     f.render_widget(block, size);
 
-    let titles = ["Departures", "Station List"]
+    let tab_names = ["Departures", "Station List", "Disruptions"];
+    app.areas.tabs = chunks[0];
+    app.areas.tab_titles = tab_title_columns(&tab_names, chunks[0]);
+
+    let titles = tab_names
         .iter()
         .map(|t| {
             Line::from(Span::styled(
-                format!("{}", t),
-                Style::default().fg(Color::LightCyan),
+                t.to_string(),
+                Style::default().fg(app.theme.accent),
             ))
         })
         .collect();
@@ -41,9 +58,19 @@ pub fn render(app: &mut App, f: &mut Frame) {
     let index: usize = match app.selected_tab {
         AppTabs::HomeTab => 0,
         AppTabs::StationTab => 1,
+        AppTabs::MessagesTab => 2,
     };
 
-    let itemlist = components::station_list::get_station_list_widget(app);
+    let list_height = chunks[1].height.saturating_sub(1) as usize; // minus the top border
+    app.areas.station_list = Rect {
+        y: chunks[1].y + 1,
+        height: chunks[1].height.saturating_sub(1),
+        ..chunks[1]
+    };
+    app.station_page_size = (list_height / 2).max(1); // every station takes two lines
+    let list_offset = app.scroll_station_rows(list_height);
+
+    let itemlist = components::station_list::get_station_list_widget(app, list_offset, list_height);
 
     let tabs = Tabs::new(titles)
         .block(
@@ -53,59 +80,137 @@ pub fn render(app: &mut App, f: &mut Frame) {
         )
         .select(index)
         .style(Style::default())
-        .highlight_style(Style::default().fg(Color::Green));
+        .highlight_style(Style::default().fg(app.theme.selected_tab));
 
     f.render_widget(tabs, chunks[0]);
 
-    let list_state = &mut app.scroll_state.clone(); //we can clone this value, because it is cheap and the function is called only once per frame
+    // the list also contains group headers and only starts at the offset,
+    // so the selected station is translated to its row among the rendered ones
+    let list_state = &mut ListState::default();
+    list_state.select(
+        app.scroll_state
+            .selected()
+            .and_then(|position| app.station_row_of.get(position))
+            .map(|row| row - list_offset),
+    );
 
     match app.selected_tab {
-        AppTabs::HomeTab => draw_departures(f, app),
+        AppTabs::HomeTab => draw_departures(f, app, chunks[1]),
         AppTabs::StationTab => f.render_stateful_widget(itemlist, chunks[1], list_state),
+        AppTabs::MessagesTab => draw_messages(f, app, chunks[1]),
     };
 
     //Status bar
 
-    let app_mode_indicator: Vec<Span> = match app.app_mode {
+    let mut app_mode_indicator: Vec<Span> = match app.app_mode {
         crate::app::AppMode::Normal => {
+            let mut spans = vec![
+                Span::styled(
+                    " NORMAL ".to_string(),
+                    Style::default().bg(Color::Blue).bold(),
+                ),
+                Span::styled(app.keymap.status_help(app.app_mode), Style::default()),
+                Span::styled(
+                    format!("Last refreshed: {}", &app.last_refreshed),
+                    Style::default().fg(app.theme.accent),
+                ),
+            ];
+            if !app.data_age.is_empty() {
+                // old ETAs shouldn't look current when the network is gone
+                let style = if app.data_stale {
+                    Style::default().fg(Color::Black).bg(Color::LightRed)
+                } else {
+                    Style::default().fg(app.theme.muted)
+                };
+                let retrying = if *app.refresh_failures.borrow() > 0 {
+                    ", retrying"
+                } else {
+                    ""
+                };
+                spans.push(Span::styled(
+                    format!(" {}{} ", app.data_age, retrying),
+                    style,
+                ));
+            }
+            #[cfg(feature = "offline-stations")]
+            if app.station_source == Some(crate::api::StationSource::Snapshot) {
+                spans.push(Span::styled(
+                    format!(" Offline stations from {} ", crate::snapshot::SNAPSHOT_DATE),
+                    Style::default().fg(Color::Black).bg(Color::LightYellow),
+                ));
+            }
+            spans
+        }
+        crate::app::AppMode::Details => {
             vec![
-            Span::styled(format!(" NORMAL "), Style::default().bg(Color::Blue).bold()),
-            Span::styled(
-            format!(" Q: close app. Tab: switch tabs. Enter: select station. R: reload departures. S: search. "),
-            Style::default()),
-            Span::styled(
-            format!("Last refreshed: {}", &app.last_refreshed),
-            Style::default().fg(Color::LightCyan))]
+                Span::styled(" DETAILS ", Style::default().bg(Color::Magenta).bold()),
+                Span::styled(app.keymap.status_help(app.app_mode), Style::default()),
+            ]
+        }
+        crate::app::AppMode::Help => {
+            vec![
+                Span::styled(" HELP ", Style::default().bg(Color::Green).bold()),
+                Span::styled(app.keymap.status_help(app.app_mode), Style::default()),
+            ]
+        }
+        crate::app::AppMode::Alerts => {
+            vec![
+                Span::styled(" ALERTS ", Style::default().bg(Color::LightRed).bold()),
+                Span::styled(app.keymap.status_help(app.app_mode), Style::default()),
+            ]
         }
         crate::app::AppMode::Search => {
             vec![
-                Span::styled(format!(" SEARCH "), Style::default().bg(Color::Red).bold()),
                 Span::styled(
-                    format!(
-                        " Esc: back to normal mode. Up/Down: navigate. Enter: select station. "
-                    ),
-                    Style::default(),
+                    " SEARCH ".to_string(),
+                    Style::default().bg(Color::Red).bold(),
                 ),
+                Span::styled(app.keymap.status_help(app.app_mode), Style::default()),
+            ]
+        }
+        crate::app::AppMode::Command => {
+            vec![
+                Span::styled(" COMMAND ", Style::default().bg(Color::Cyan).bold()),
+                Span::styled(app.keymap.status_help(app.app_mode), Style::default()),
             ]
         }
     };
 
+    // a command can leave the app in any mode, i.e. help opens the keys
+    if let Some(notice) = &app.notice {
+        app_mode_indicator.push(Span::styled(
+            format!(" {} ", notice),
+            Style::default().fg(Color::Black).bg(Color::LightYellow),
+        ));
+    }
+
     let status_bar = Line::from(app_mode_indicator);
 
-    f.render_widget(Paragraph::new(status_bar), chunks[2]);
+    app.areas.ticker = chunks[2];
+    f.render_widget(
+        get_ticker_widget(&app.alerts, app.ticker_offset, chunks[2].width as usize),
+        chunks[2],
+    );
+    // blinks for a moment when an alarm goes off
+    let status_style = if app.flash_ticks % 2 == 1 {
+        Style::default().bg(Color::LightRed)
+    } else {
+        Style::default()
+    };
+    f.render_widget(Paragraph::new(status_bar).style(status_style), chunks[3]);
 
     //SEARCH MODAL
     //todo: move to its own component
 
     if app.app_mode == crate::app::AppMode::Search {
-        let popup_title = " ⌕ Search for a station ";
+        let popup_title = " ⌕ Search for a station (filters: zone:1 place:Freising has:ubahn) ";
 
         let mut text = Text::from(Line::from(app.query.clone()));
         text.patch_style(Style::default().add_modifier(Modifier::RAPID_BLINK));
 
         let input_field = Paragraph::new(text)
             .block(Block::default().borders(Borders::ALL).title(popup_title))
-            .style(Style::default().fg(Color::LightCyan))
+            .style(Style::default().fg(app.theme.accent))
             .alignment(ratatui::prelude::Alignment::Left);
 
         let area = static_widgets::centered_rect(69, 50, f.size()); //size of the MODAL
@@ -127,32 +232,198 @@ pub fn render(app: &mut App, f: &mut Frame) {
 
         //search suggestion section
 
-        let search_scroll_state = &mut app.search_scroll_state.clone();
+        let mut search_scroll_state = app.search_scroll_state.clone();
         let suggested_stations = get_suggested_station_list(app).highlight_style(
             Style::default()
-                .bg(Color::Rgb(38, 35, 53))
+                .bg(app.theme.highlight)
                 .add_modifier(Modifier::BOLD),
         );
 
-        f.render_stateful_widget(suggested_stations, chunks[1], search_scroll_state);
+        f.render_stateful_widget(suggested_stations, chunks[1], &mut search_scroll_state);
+        app.search_scroll_state = search_scroll_state; // keeps the scroll offset for mouse clicks
+        app.areas.suggestions = chunks[1];
+    }
+
+    //COMMAND PALETTE, laid out like the search modal
+
+    if app.app_mode == crate::app::AppMode::Command {
+        let input_field = Paragraph::new(Line::from(vec![
+            Span::raw(":"),
+            Span::raw(app.query.clone()),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Command (Tab completes) "),
+        )
+        .style(Style::default().fg(app.theme.accent));
+
+        let area = static_widgets::centered_rect(69, 50, f.size());
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        f.render_widget(Clear, area);
+        f.render_widget(input_field, chunks[0]);
+        // one column for the border and one for the ":"
+        f.set_cursor(
+            chunks[0].x + app.cursor_position as u16 + 2,
+            chunks[0].y + 1,
+        );
+
+        let mut command_scroll_state = app.command_scroll_state.clone();
+        let suggestions = get_command_suggestion_list(app).highlight_style(
+            Style::default()
+                .bg(app.theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        );
+        f.render_stateful_widget(suggestions, chunks[1], &mut command_scroll_state);
+        app.command_scroll_state = command_scroll_state;
+        app.areas.suggestions = chunks[1];
+    }
+
+    //DEPARTURE DETAILS POPUP
+
+    if app.app_mode == crate::app::AppMode::Details {
+        if let Some(departure) = app.selected_departure() {
+            let area = static_widgets::centered_rect(50, 50, f.size());
+            f.render_widget(Clear, area);
+            f.render_widget(get_departure_details_widget(departure, &app.theme), area);
+        }
+    }
+
+    //ALERTS POPUP
+
+    if app.app_mode == crate::app::AppMode::Alerts {
+        let area = static_widgets::centered_rect(60, 80, f.size());
+        // the keys scroll freely, the box stops at its last line
+        app.alerts_scroll = app
+            .alerts_scroll
+            .min(alerts_max_scroll(&app.alerts, &app.theme, area));
+        f.render_widget(Clear, area);
+        f.render_widget(
+            get_alerts_widget(&app.alerts, &app.theme, app.alerts_scroll),
+            area,
+        );
+    }
+
+    //HELP POPUP
+
+    if app.app_mode == crate::app::AppMode::Help {
+        let area = static_widgets::centered_rect(60, 80, f.size());
+        app.help_scroll = app
+            .help_scroll
+            .min(help_max_scroll(&app.keymap, &app.theme, area));
+        f.render_widget(Clear, area);
+        f.render_widget(
+            get_help_widget(&app.keymap, &app.theme, app.help_scroll),
+            area,
+        );
     }
 }
 
-fn draw_departures(f: &mut Frame<'_>, app: &App) {
-    let popup_title = match &app.selected_station {
+// the columns the Tabs widget draws every title in, including the padding around it
+fn tab_title_columns(names: &[&str], area: Rect) -> Vec<Range<u16>> {
+    let mut x = area.x + 1; // the border
+    names
+        .iter()
+        .map(|name| {
+            let width = name.chars().count() as u16 + 2;
+            let columns = x..x + width;
+            x += width + 1; // the divider
+            columns
+        })
+        .collect()
+}
+
+// body is the space between the tabs and the status bar
+fn draw_departures(f: &mut Frame<'_>, app: &mut App, body: Rect) {
+    let breakpoint = Breakpoint::of(f.size().width);
+
+    let mut popup_title = match &app.selected_station {
         Some(station) => format!(" {} ", station.name),
         None => " No station selected ".to_string(),
     };
+    if app.departure_grouping != DepartureGrouping::None {
+        popup_title.push_str(&format!("({}) ", app.departure_grouping.label()));
+    }
+    if let Some(stop_point) = app
+        .departures
+        .iter()
+        .find(|departure| app.stop_point_filter.as_ref() == Some(&departure.stop_point_global_id))
+    {
+        popup_title.push_str(&format!("({} only) ", stop_point.stop_point_name()));
+    }
+    if let Some(filter) = &app.departure_filter {
+        popup_title.push_str(&format!("(filter: {}) ", filter));
+    }
+    if let Some(from) = app.departures_from {
+        popup_title.push_str(&format!("(from {}) ", format_day_time(from)));
+    }
+    if !app.transport_types.is_empty() {
+        popup_title.push_str(&format!(
+            "({} only) ",
+            app.transport_types.join(", ").to_lowercase()
+        ));
+    }
 
+    let padding = match breakpoint {
+        Breakpoint::Compact => Padding::new(1, 1, 0, 0),
+        _ => Padding::new(2, 2, 1, 1),
+    };
     let block = Block::default()
         .title(popup_title)
         .borders(Borders::ALL)
-        .padding(Padding::new(2, 2, 1, 1))
+        .padding(padding)
         .style(Style::default());
 
-    let table = display_departures_table(&app.departures).block(block);
+    let area = breakpoint.departures_area(body, f.size());
+    let inner = block.inner(area);
+    app.areas.departure_rows = Rect {
+        y: inner.y + DEPARTURE_HEADER_HEIGHT,
+        height: inner.height.saturating_sub(DEPARTURE_HEADER_HEIGHT),
+        ..inner
+    };
+
+    let mut departure_scroll_state = app.departure_scroll_state.clone();
+    let departure_rows = app.departure_rows();
+    let table = display_departures_table(
+        &departure_rows,
+        &app.theme,
+        breakpoint,
+        app.departure_grouping == DepartureGrouping::Line,
+        app.time_display(),
+        &app.alarms,
+        &app.departure_changes,
+    )
+    .block(block);
 
-    let area = static_widgets::centered_rect(80, 69, f.size());
     f.render_widget(Clear, area); //this clears out the background
-    f.render_widget(table, area);
+    f.render_stateful_widget(table, area, &mut departure_scroll_state);
+    app.departure_scroll_state = departure_scroll_state;
+}
+
+fn draw_messages(f: &mut Frame<'_>, app: &mut App, area: Rect) {
+    let mut title = format!(" {} messages ", app.message_provider.name());
+    if let Some(line) = &app.message_line_filter {
+        title.push_str(&format!("(line {}) ", line));
+    }
+    if app.messages_favorites_only {
+        title.push_str("(favorite stations) ");
+    }
+    match &app.messages_refreshed {
+        Some(time) => title.push_str(&format!("· {} ", time)),
+        None => title.push_str("· loading "),
+    }
+
+    let messages = app.visible_messages();
+    let scroll = app
+        .messages_scroll
+        .min(messages_max_scroll(&messages, &app.theme, area));
+    f.render_widget(
+        get_messages_widget(&messages, &app.theme, title, scroll),
+        area,
+    );
+    app.messages_scroll = scroll;
 }
@@ -1,98 +1,443 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::widgets::ListState;
 
 use crate::{
-    app::{App, AppMode},
+    app::{App, AppMode, AppTabs, LOOK_AHEAD_STEP_MINUTES},
     event::Event,
+    keymap::Action,
 };
 
 //todo: should_redraw refactor
+// keys are looked up in the active keymap, see keymap.rs for the bindings
 pub async fn update(app: &mut App, key_event: KeyEvent) {
+    let action = app.keymap.action(app.app_mode, &key_event);
+
     match app.app_mode {
-        AppMode::Normal => match key_event.code {
-            KeyCode::Char('q') => app.quit(),
-            KeyCode::Char('s') => {
-                app.app_mode = AppMode::Search;
+        AppMode::Normal => {
+            if app.notice.take().is_some() {
+                app.should_redraw = true;
+            }
+            if let KeyCode::Char(c) = key_event.code {
+                let is_typed = !key_event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                if is_typed && app.accepts_type_ahead(c, action.is_some()) {
+                    app.type_ahead(c);
+                    app.should_redraw = true;
+                    return;
+                }
+            }
+
+            match action {
+                Some(Action::Quit) => app.quit(),
+                Some(Action::Search) => {
+                    app.app_mode = AppMode::Search;
+                    app.update_suggestions();
+                    app.should_redraw = true;
+                }
+                Some(Action::Refresh) => match app.selected_tab {
+                    AppTabs::MessagesTab => app.update_network_messages().await,
+                    _ => app.update_departures().await,
+                },
+                Some(Action::Down) => {
+                    match app.selected_tab {
+                        AppTabs::HomeTab => app.next_departure(),
+                        AppTabs::StationTab => app.increment_station(),
+                        AppTabs::MessagesTab => {
+                            app.messages_scroll = app.messages_scroll.saturating_add(1)
+                        }
+                    }
+                    app.should_redraw = true;
+                }
+                Some(Action::Up) => {
+                    match app.selected_tab {
+                        AppTabs::HomeTab => app.previous_departure(),
+                        AppTabs::StationTab => app.decrement_station(),
+                        AppTabs::MessagesTab => {
+                            app.messages_scroll = app.messages_scroll.saturating_sub(1)
+                        }
+                    }
+                    app.should_redraw = true;
+                }
+                Some(Action::PageDown) => {
+                    app.page_down_stations();
+                    app.should_redraw = true;
+                }
+                Some(Action::PageUp) => {
+                    app.page_up_stations();
+                    app.should_redraw = true;
+                }
+                Some(Action::First) => {
+                    app.first_station();
+                    app.should_redraw = true;
+                }
+                Some(Action::Last) => {
+                    app.last_station();
+                    app.should_redraw = true;
+                }
+                Some(Action::GroupStations) => {
+                    app.toggle_station_grouping();
+                    app.should_redraw = true;
+                }
+                Some(Action::GroupDepartures) => {
+                    app.toggle_departure_grouping();
+                    app.should_redraw = true;
+                }
+                Some(Action::FilterStopPoint) => {
+                    app.toggle_stop_point_filter();
+                    app.should_redraw = true;
+                }
+                Some(Action::ClockTimes) => {
+                    app.toggle_clock_times();
+                    app.should_redraw = true;
+                }
+                Some(Action::Later) => {
+                    app.look_ahead(LOOK_AHEAD_STEP_MINUTES).await;
+                    app.should_redraw = true;
+                }
+                Some(Action::Earlier) => {
+                    app.look_ahead(-LOOK_AHEAD_STEP_MINUTES).await;
+                    app.should_redraw = true;
+                }
+                Some(Action::Select) => {
+                    match app.selected_tab {
+                        AppTabs::HomeTab => app.open_departure_details(),
+                        AppTabs::StationTab => app.select_station().await,
+                        AppTabs::MessagesTab => {}
+                    }
+                    app.should_redraw = true;
+                }
+                Some(Action::SwitchTab) => {
+                    app.toggle_tabs().await;
+                    app.should_redraw = true;
+                }
+                Some(Action::Help) => {
+                    app.open_help();
+                    app.should_redraw = true;
+                }
+                Some(Action::CommandPalette) => {
+                    app.open_command_palette("");
+                    app.should_redraw = true;
+                }
+                Some(Action::TimeTravel) => {
+                    app.open_command_palette("at ");
+                    app.should_redraw = true;
+                }
+                Some(Action::Alerts) => {
+                    app.open_alerts();
+                    app.should_redraw = true;
+                }
+                Some(Action::Alarm) => {
+                    if let Err(error) = app.toggle_alarm(None) {
+                        app.notice = Some(error.to_string());
+                    }
+                    app.should_redraw = true;
+                }
+                Some(Action::FavoriteMessages) if app.selected_tab == AppTabs::MessagesTab => {
+                    app.toggle_favorite_messages();
+                    app.should_redraw = true;
+                }
+                _ => {
+                    // todo: pass the key event?
+                }
+            }
+        }
+        AppMode::Search => match action {
+            Some(Action::Select) => {
+                app.select_searched_station().await;
+                app.should_redraw = true;
+            }
+            Some(Action::ClearHistory) => {
+                app.search_scroll_state = ListState::default();
+                app.clear_recent_stations();
                 app.should_redraw = true;
             }
-            KeyCode::Char('r') => {
-                app.update_departures().await;
-                // app.should_redraw = true;
+            Some(Action::DeleteChar) => {
+                app.search_scroll_state = ListState::default();
+                app.delete_char();
+                app.update_suggestions();
+                app.should_redraw = true;
             }
-            KeyCode::Down => {
-                app.increment_station();
+            Some(Action::Down) => {
+                app.scroll_down();
+                app.should_redraw = true;
+            }
+            Some(Action::Up) => {
+                app.scroll_up();
                 app.should_redraw = true;
             }
-            KeyCode::Up => {
-                app.decrement_station();
+            Some(Action::CursorLeft) => {
+                app.move_cursor_left();
                 app.should_redraw = true;
             }
-            KeyCode::Enter => {
-                app.select_station().await;
+            Some(Action::CursorRight) => {
+                app.move_cursor_right();
                 app.should_redraw = true;
             }
-            KeyCode::Tab => {
-                app.toggle_tabs();
+            Some(Action::Back) => {
+                app.app_mode = AppMode::Normal;
                 app.should_redraw = true;
             }
-            KeyCode::Char('c') | KeyCode::Char('C') => {
-                if key_event.modifiers == KeyModifiers::CONTROL {
-                    app.quit()
+            Some(_) => {}
+            // everything that isn't bound is typed into the search field
+            None => {
+                if let KeyCode::Char(to_insert) = key_event.code {
+                    if !key_event
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                    {
+                        app.search_scroll_state = ListState::default();
+                        app.enter_char(to_insert);
+                        app.update_suggestions();
+                        app.should_redraw = true;
+                    }
                 }
             }
-            _ => {
-                // todo: pass the key event?
+        },
+        AppMode::Details => match action {
+            Some(Action::Back) => {
+                app.app_mode = AppMode::Normal;
+                app.should_redraw = true;
+            }
+            Some(Action::Down) => {
+                app.next_departure();
+                app.should_redraw = true;
+            }
+            Some(Action::Up) => {
+                app.previous_departure();
+                app.should_redraw = true;
             }
+            _ => {}
         },
-        AppMode::Search => match key_event.code {
-            KeyCode::Enter => {
-                app.select_searched_station().await;
+        AppMode::Help => match action {
+            Some(Action::Back) => {
+                app.app_mode = AppMode::Normal;
                 app.should_redraw = true;
             }
-            KeyCode::Char(to_insert) => {
-                app.search_scroll_state = ListState::default();
-                app.enter_char(to_insert);
+            Some(Action::Down) => {
+                app.help_scroll = app.help_scroll.saturating_add(1);
                 app.should_redraw = true;
             }
-            KeyCode::Backspace => {
-                app.search_scroll_state = ListState::default();
+            Some(Action::Up) => {
+                app.help_scroll = app.help_scroll.saturating_sub(1);
+                app.should_redraw = true;
+            }
+            _ => {}
+        },
+        AppMode::Alerts => match action {
+            Some(Action::Back) => {
+                app.app_mode = AppMode::Normal;
+                app.should_redraw = true;
+            }
+            Some(Action::Down) => {
+                app.alerts_scroll = app.alerts_scroll.saturating_add(1);
+                app.should_redraw = true;
+            }
+            Some(Action::Up) => {
+                app.alerts_scroll = app.alerts_scroll.saturating_sub(1);
+                app.should_redraw = true;
+            }
+            _ => {}
+        },
+        AppMode::Command => match action {
+            Some(Action::Select) => {
+                app.run_command().await;
+                app.should_redraw = true;
+            }
+            Some(Action::Complete) => {
+                app.complete_command();
+                app.should_redraw = true;
+            }
+            Some(Action::DeleteChar) => {
                 app.delete_char();
+                app.update_command_suggestions();
                 app.should_redraw = true;
             }
-            KeyCode::Down => {
-                app.scroll_down();
+            Some(Action::Down) => {
+                app.next_command_suggestion();
                 app.should_redraw = true;
             }
-            KeyCode::Up => {
-                app.scroll_up();
+            Some(Action::Up) => {
+                app.previous_command_suggestion();
                 app.should_redraw = true;
             }
-            KeyCode::Left => {
+            Some(Action::CursorLeft) => {
                 app.move_cursor_left();
                 app.should_redraw = true;
             }
-            KeyCode::Right => {
+            Some(Action::CursorRight) => {
                 app.move_cursor_right();
                 app.should_redraw = true;
             }
-            KeyCode::Esc => {
+            Some(Action::Back) => {
                 app.app_mode = AppMode::Normal;
+                app.query.clear();
+                app.reset_cursor();
                 app.should_redraw = true;
             }
-            _ => {}
+            Some(_) => {}
+            None => {
+                if let KeyCode::Char(to_insert) = key_event.code {
+                    if !key_event
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                    {
+                        app.enter_char(to_insert);
+                        app.update_command_suggestions();
+                        app.should_redraw = true;
+                    }
+                }
+            }
         },
     }
 }
 
+// mouse events are mapped onto the same app methods as the keys
+pub async fn handle_mouse(app: &mut App, mouse_event: MouseEvent) {
+    let (column, row) = (mouse_event.column, mouse_event.row);
+
+    match mouse_event.kind {
+        MouseEventKind::ScrollDown => match app.app_mode {
+            AppMode::Search => app.scroll_down(),
+            AppMode::Command => app.next_command_suggestion(),
+            AppMode::Details => app.next_departure(),
+            AppMode::Help => app.help_scroll = app.help_scroll.saturating_add(1),
+            AppMode::Alerts => app.alerts_scroll = app.alerts_scroll.saturating_add(1),
+            AppMode::Normal => match app.selected_tab {
+                AppTabs::HomeTab => app.next_departure(),
+                AppTabs::StationTab => app.increment_station(),
+                AppTabs::MessagesTab => app.messages_scroll = app.messages_scroll.saturating_add(1),
+            },
+        },
+        MouseEventKind::ScrollUp => match app.app_mode {
+            AppMode::Search => app.scroll_up(),
+            AppMode::Command => app.previous_command_suggestion(),
+            AppMode::Details => app.previous_departure(),
+            AppMode::Help => app.help_scroll = app.help_scroll.saturating_sub(1),
+            AppMode::Alerts => app.alerts_scroll = app.alerts_scroll.saturating_sub(1),
+            AppMode::Normal => match app.selected_tab {
+                AppTabs::HomeTab => app.previous_departure(),
+                AppTabs::StationTab => app.decrement_station(),
+                AppTabs::MessagesTab => app.messages_scroll = app.messages_scroll.saturating_sub(1),
+            },
+        },
+        MouseEventKind::Down(MouseButton::Left) => match app.app_mode {
+            AppMode::Search => {
+                if let Some(i) = app.suggestion_at(column, row) {
+                    app.search_scroll_state.select(Some(i));
+                    app.select_searched_station().await;
+                }
+            }
+            AppMode::Command => {
+                if let Some(i) = app.suggestion_at(column, row) {
+                    app.command_scroll_state.select(Some(i));
+                    app.run_command().await;
+                }
+            }
+            // clicking anywhere closes the popups
+            AppMode::Details | AppMode::Help | AppMode::Alerts => app.app_mode = AppMode::Normal,
+            AppMode::Normal => {
+                if let Some(tab) = app.tab_at(column, row) {
+                    app.select_tab(tab).await;
+                } else if app.selected_tab == AppTabs::StationTab {
+                    if let Some(position) = app.station_at(column, row) {
+                        app.scroll_state.select(Some(position));
+                        app.select_station().await;
+                    }
+                } else if app.selected_tab == AppTabs::MessagesTab {
+                    // the messages are just text
+                } else if let Some(i) = app.departure_at(column, row) {
+                    app.departure_scroll_state.select(Some(i));
+                    app.open_departure_details();
+                }
+            }
+        },
+        _ => return, // mouse moves are frequent and don't change anything
+    }
+    app.should_redraw = true;
+}
+
 // this lets us mutate the app state without having to pass a mutable reference and blocking the main ui/event thread or having to use a mutex
-// we simulate the refresh command by sending a key event to the event handler
-// the event handler has a mutable reference to the app and can mutate it
-pub fn initiate_auto_refresh(sender: tokio::sync::mpsc::UnboundedSender<Event>) {
+// we send a refresh event to the event handler, the main loop has a mutable reference to the app and can mutate it
+// it is a separate event from the `r` key, so that the app can skip it while the terminal is in the background
+// the app reports every refresh through `failures`, the timer starts over with the delay that fits the outcome
+pub fn initiate_auto_refresh(
+    sender: tokio::sync::mpsc::UnboundedSender<Event>,
+    mut failures: tokio::sync::watch::Receiver<u32>,
+) {
     tokio::spawn(async move {
         loop {
-            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-            // println!("sending refresh event");
-            let _ = sender.send(Event::Key(KeyEvent::from(KeyCode::Char('r'))));
+            let delay = retry_delay(*failures.borrow_and_update());
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {
+                    if sender.send(Event::Refresh).is_err() {
+                        return; // the app has quit
+                    }
+                }
+                changed = failures.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                }
+            }
         }
     });
 }
+
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+// the first retry after a failed refresh
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+// the regular interval while refreshes work, then 5s, 10s, 20s... up to 5 minutes
+// retries wait a random time between half and all of their delay, so that many clients don't retry in lockstep
+fn retry_delay(failures: u32) -> Duration {
+    if failures == 0 {
+        return REFRESH_INTERVAL;
+    }
+    let delay = FIRST_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(failures - 1))
+        .min(MAX_RETRY_DELAY);
+    // the nanoseconds of the clock differ from client to client, that is random enough to spread the retries
+    let random = chrono::Utc::now().timestamp_subsec_nanos() % 1000;
+    delay / 2 + delay.mul_f64(random as f64 / 2000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_between(delay: Duration, min: u64, max: u64) {
+        assert!(
+            delay >= Duration::from_secs(min) && delay <= Duration::from_secs(max),
+            "{:?} is not between {}s and {}s",
+            delay,
+            min,
+            max
+        );
+    }
+
+    #[test]
+    fn refreshes_regularly_while_they_work() {
+        assert_eq!(retry_delay(0), REFRESH_INTERVAL);
+    }
+
+    #[test]
+    fn doubles_the_delay_with_every_failure() {
+        assert_between(retry_delay(1), 2, 5);
+        assert_between(retry_delay(2), 5, 10);
+        assert_between(retry_delay(3), 10, 20);
+        assert_between(retry_delay(4), 20, 40);
+    }
+
+    #[test]
+    fn the_delay_is_capped() {
+        assert_between(retry_delay(7), 150, 300);
+        assert_between(retry_delay(40), 150, 300);
+        assert_between(retry_delay(u32::MAX), 150, 300);
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashMap, fs::File};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::app::DepartureGrouping;
+
+// stored next to stations.json
+const USER_DATA_FILE: &str = "user_data.json";
+
+const MAX_RECENT_STATIONS: usize = 10;
+
+// everything the app remembers between runs
+// new fields need #[serde(default)] so that older files keep loading
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserData {
+    #[serde(default)]
+    pub recent_stations: Vec<String>, // station ids, most recent first
+    #[serde(default)]
+    pub favorite_stations: Vec<String>, // station ids, in the order they were added
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub departure_groupings: HashMap<String, DepartureGrouping>, // by station id, ungrouped stations are left out
+    #[serde(default)]
+    pub clock_times: bool, // departure times as HH:MM instead of minutes from now
+    #[serde(default, skip_serializing)]
+    grouped_stations: Vec<String>, // older files listed the stations grouped by line, see load
+}
+
+impl UserData {
+    // a missing or broken file just means there is nothing to remember yet
+    pub fn load() -> Self {
+        File::open(USER_DATA_FILE)
+            .ok()
+            .and_then(|file| serde_json::from_reader::<_, Self>(file).ok())
+            .map(|mut user_data| {
+                for id in std::mem::take(&mut user_data.grouped_stations) {
+                    user_data
+                        .departure_groupings
+                        .entry(id)
+                        .or_insert(DepartureGrouping::Line);
+                }
+                user_data
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(USER_DATA_FILE, json)?;
+        Ok(())
+    }
+
+    pub fn add_recent_station(&mut self, id: &str) {
+        self.recent_stations.retain(|recent| recent != id);
+        self.recent_stations.insert(0, id.to_string());
+        self.recent_stations.truncate(MAX_RECENT_STATIONS);
+    }
+
+    pub fn clear_recent_stations(&mut self) {
+        self.recent_stations.clear();
+    }
+
+    // false if the station already was a favorite
+    pub fn add_favorite_station(&mut self, id: &str) -> bool {
+        if self.favorite_stations.iter().any(|favorite| favorite == id) {
+            return false;
+        }
+        self.favorite_stations.push(id.to_string());
+        true
+    }
+
+    // false if the station wasn't a favorite
+    pub fn remove_favorite_station(&mut self, id: &str) -> bool {
+        let count = self.favorite_stations.len();
+        self.favorite_stations.retain(|favorite| favorite != id);
+        self.favorite_stations.len() != count
+    }
+
+    pub fn departure_grouping(&self, id: &str) -> DepartureGrouping {
+        self.departure_groupings
+            .get(id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set_departure_grouping(&mut self, id: &str, grouping: DepartureGrouping) {
+        match grouping {
+            DepartureGrouping::None => self.departure_groupings.remove(id),
+            _ => self.departure_groupings.insert(id.to_string(), grouping),
+        };
+    }
+}